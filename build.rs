@@ -1,10 +1,204 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// riscv-isa-sim versions the shim has been checked against.
+const SUPPORTED_SPIKE_VERSIONS: &[&str] = &["1.1.1-dev"];
+
+/// Spike's libraries, in link order.
+const SPIKE_LIBS: &[&str] = &["riscv", "softfloat", "disasm"];
+
+struct Spike {
+    includes: Vec<PathBuf>,
+    /// Printed after the shim is built, so that it comes first on the link line.
+    cargo_metadata: Vec<String>,
+}
+
 fn main() {
-    println!("cargo:rustc-link-search=native=target");
-    println!("cargo:rustc-link-search=native=deps/riscv-isa-sim/build");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cpp");
+    println!("cargo:rerun-if-env-changed=SPIKE_DIR");
+
+    // Spike is taken from, in order: the prefix in SPIKE_DIR, pkg-config when
+    // the `system-spike` feature is enabled, or the submodule.
+    let spike = if let Some(dir) = env::var_os("SPIKE_DIR") {
+        installed_spike(Path::new(&dir))
+    } else if env::var_os("CARGO_FEATURE_SYSTEM_SPIKE").is_some() {
+        pkg_config_spike()
+    } else {
+        submodule_spike()
+    };
+
+    check_version(&spike.includes);
+    build_shim(&spike.includes);
+    generate_ffi();
+    for line in spike.cargo_metadata {
+        println!("cargo:{}", line);
+    }
+}
+
+fn run(cmd: &mut Command) {
+    let status = cmd
+        .status()
+        .unwrap_or_else(|e| panic!("failed to run {:?}: {}", cmd, e));
+    assert!(status.success(), "{:?} failed: {}", cmd, status);
+}
+
+/// Configure and build riscv-isa-sim out of tree in OUT_DIR. Only the static
+/// libraries are built, so the submodule stays untouched.
+fn submodule_spike() -> Spike {
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let spike = root.join("deps/riscv-isa-sim");
+    let build = PathBuf::from(env::var("OUT_DIR").unwrap()).join("riscv-isa-sim");
+    println!("cargo:rerun-if-changed=deps/riscv-isa-sim");
 
-    println!("cargo:rustc-link-lib=static=riscv");
-    println!("cargo:rustc-link-lib=static=softfloat");
-    println!("cargo:rustc-link-lib=static=disasm");
+    assert!(
+        spike.join("configure").exists(),
+        "{} is empty, run `git submodule update --init` or set SPIKE_DIR",
+        spike.display()
+    );
+    fs::create_dir_all(&build).unwrap();
+
+    let cc = cc::Build::new().get_compiler();
+    let cxx = cc::Build::new().cpp(true).get_compiler();
+    if !build.join("Makefile").exists() {
+        run(Command::new(spike.join("configure"))
+            .current_dir(&build)
+            .arg(format!("CC={}", cc.path().display()))
+            .arg(format!("CXX={}", cxx.path().display()))
+            .arg("CFLAGS=-g -O1 -fPIC")
+            .arg("CXXFLAGS=-g -O1 -fPIC"));
+    }
+    let jobs = env::var("NUM_JOBS").unwrap_or_else(|_| "1".to_string());
+    run(Command::new("make")
+        .current_dir(&build)
+        .arg(format!("-j{}", jobs))
+        .args(SPIKE_LIBS.iter().map(|l| format!("lib{}.a", l))));
+
+    let mut cargo_metadata = vec![format!("rustc-link-search=native={}", build.display())];
+    for lib in SPIKE_LIBS {
+        cargo_metadata.push(format!("rustc-link-lib=static={}", lib));
+    }
+    Spike {
+        includes: vec![
+            spike.join("riscv"),
+            build,
+            spike.join("softfloat"),
+            spike.join("fesvr"),
+        ],
+        cargo_metadata,
+    }
+}
+
+/// Spike installed with `make install` under `prefix`, e.g. /opt/riscv.
+fn installed_spike(prefix: &Path) -> Spike {
+    let include = prefix.join("include");
+    assert!(
+        include.join("riscv").join("processor.h").exists(),
+        "no riscv-isa-sim headers in {}",
+        include.display()
+    );
+    let mut cargo_metadata = vec![];
+    for lib in ["lib", "lib64"].iter() {
+        if prefix.join(lib).exists() {
+            cargo_metadata.push(format!(
+                "rustc-link-search=native={}",
+                prefix.join(lib).display()
+            ));
+        }
+    }
+    for lib in SPIKE_LIBS {
+        cargo_metadata.push(format!("rustc-link-lib={}", lib));
+    }
+    Spike {
+        includes: vec![
+            include.join("riscv"),
+            include.clone(),
+            include.join("softfloat"),
+            include.join("fesvr"),
+        ],
+        cargo_metadata,
+    }
+}
+
+/// Spike found through the pkg-config files installed with it.
+fn pkg_config_spike() -> Spike {
+    let mut includes = vec![];
+    let mut cargo_metadata = vec![];
+    for name in ["riscv-riscv", "riscv-disasm"].iter() {
+        let lib = pkg_config::Config::new()
+            .cargo_metadata(false)
+            .probe(name)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "riscv-isa-sim not found by pkg-config ({}), set PKG_CONFIG_PATH or SPIKE_DIR",
+                    e
+                )
+            });
+        for path in lib.link_paths {
+            cargo_metadata.push(format!("rustc-link-search=native={}", path.display()));
+        }
+        for l in lib.libs {
+            cargo_metadata.push(format!("rustc-link-lib={}", l));
+        }
+        for include in lib.include_paths {
+            for sub in ["riscv", "softfloat", "fesvr"].iter() {
+                includes.push(include.join(sub));
+            }
+            includes.push(include);
+        }
+    }
+    // softfloat has no .pc file, it's installed next to libriscv.
+    cargo_metadata.push("rustc-link-lib=softfloat".to_string());
+    Spike {
+        includes,
+        cargo_metadata,
+    }
+}
+
+/// Warn when Spike is at a version the shim hasn't been checked against. The
+/// shim only uses public members of processor_t, so API changes fail to
+/// compile rather than break silently, but behaviour may still differ.
+fn check_version(includes: &[PathBuf]) {
+    let config = includes
+        .iter()
+        .find_map(|dir| fs::read_to_string(dir.join("config.h")).ok())
+        .expect("riscv-isa-sim's config.h not found");
+    let version = config
+        .lines()
+        .find_map(|l| l.strip_prefix("#define PACKAGE_VERSION "))
+        .map(|v| v.trim().trim_matches('"').to_string())
+        .expect("PACKAGE_VERSION not found in riscv-isa-sim's config.h");
+    if !SUPPORTED_SPIKE_VERSIONS.contains(&version.as_str()) {
+        println!(
+            "cargo:warning=riscv-isa-sim {} is untested, supported versions: {:?}",
+            version, SUPPORTED_SPIKE_VERSIONS
+        );
+    }
+}
+
+/// The shim is always built from cpp/, whichever Spike it links against.
+fn build_shim(includes: &[PathBuf]) {
+    cc::Build::new()
+        .cpp(true)
+        .file("cpp/spike-interfaces.cc")
+        .includes(includes)
+        .flag_if_supported("-std=c++17")
+        .warnings(false)
+        .compile("spike-interfaces");
+}
 
-    println!("cargo:rustc-link-lib=dylib=stdc++");
+/// Generate `ffi` from the shim's header, so the two can't drift apart.
+fn generate_ffi() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindgen::Builder::default()
+        .header("cpp/spike-interfaces.h")
+        .allowlist_function("rvv_.*")
+        .allowlist_var("RVV_.*")
+        .derive_default(true)
+        .generate()
+        .expect("failed to generate bindings for cpp/spike-interfaces.h")
+        .write_to_file(out.join("ffi.rs"))
+        .unwrap();
 }
@@ -3,11 +3,12 @@ set -e
 DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" >/dev/null 2>&1 && pwd )"
 cd ${DIR}
 
-bash build.sh
-
-make
 cargo build
+# The ckb-vm adapter and the fuzz targets using it.
+cargo build --features=ckb-vm
+cargo check --manifest-path=fuzz/Cargo.toml
+cargo test
 
 for example in examples/*.rs; do
-    cargo run --example `basename $example .rs`
+    cargo run --features=gdb --example `basename $example .rs`
 done
@@ -1,7 +1,16 @@
+#include <algorithm>
+#include <atomic>
 #include <iostream>
+#include <map>
+#include <memory>
+#include <set>
+#include <stdarg.h>
 #include <stdio.h>
 #include <string>
+#include <tuple>
+#include <vector>
 
+#include "config.h"
 #include "disasm.h"
 #include "mmu.h"
 #include "processor.h"
@@ -11,83 +20,633 @@
 
 #define START_MEM 4096
 
+static std::atomic<rvv_log_callback> log_callback{NULL};
+
+void rvv_set_log_callback(rvv_log_callback callback) { log_callback = callback; }
+
+// Pass a diagnostic to the callback, or print it to stderr if there is none.
+static void log_message(int32_t level, const char *format, ...)
+    __attribute__((format(printf, 2, 3)));
+static void log_message(int32_t level, const char *format, ...) {
+  char buf[256];
+  va_list args;
+  va_start(args, format);
+  vsnprintf(buf, sizeof(buf), format, args);
+  va_end(args);
+  rvv_log_callback callback = log_callback;
+  if (callback) {
+    callback(level, buf);
+  } else {
+    fprintf(stderr, "%s\n", buf);
+  }
+}
+
+// The message of the last error on this thread, see rvv_last_error.
+static thread_local std::string last_error;
+
+const char *rvv_last_error(void) { return last_error.c_str(); }
+
+// C++ exceptions must not unwind into the caller: run f, and if it throws,
+// keep the message for rvv_last_error and return `error` instead.
+template <typename T, typename F> static T guard(T error, F f) {
+  try {
+    return f();
+  } catch (std::exception &e) {
+    last_error = e.what();
+  } catch (...) {
+    last_error = "unknown C++ exception";
+  }
+  log_message(RVV_LOG_ERROR, "%s", last_error.c_str());
+  return error;
+}
+
+template <typename F> static void guard(F f) {
+  guard(0, [&] {
+    f();
+    return 0;
+  });
+}
+
+// A processor's log stream, passed to log_message line by line.
+class log_buf : public std::streambuf {
+protected:
+  int overflow(int c) override {
+    if (c == EOF) {
+      return 0;
+    }
+    if (c == '\n') {
+      sync();
+    } else {
+      line.push_back((char)c);
+    }
+    return c;
+  }
+  int sync() override {
+    if (!line.empty()) {
+      log_message(RVV_LOG_INFO, "%s", line.c_str());
+      line.clear();
+    }
+    return 0;
+  }
+
+private:
+  std::string line;
+};
+
+struct watchpoint_t {
+  uint64_t start;
+  uint64_t end;
+  // RVV_ACCESS_READ and/or RVV_ACCESS_WRITE, 0 for none.
+  int32_t access;
+};
+
 class memory : public simif_t {
 public:
   memory(uint64_t size) {
-    mem = new uint8_t[size];
+    mem = new uint8_t[size]();
     mem_size = size;
+    dirty_start = size;
+    dirty_end = 0;
+    watching = false;
+    hit = {0, 0, 0};
   }
   ~memory() { delete[] mem; }
   virtual char *addr_to_mem(reg_t addr) { return NULL; }
   virtual bool mmio_load(reg_t addr, size_t len, uint8_t *bytes) {
-    if ((addr + len) > (mem_size + START_MEM) || addr < START_MEM) {
-      fprintf(stderr, "Out of bounds in mmio_load: addr = %lu, len = %lu\n",
-              addr, len);
+    watch(addr, len, RVV_ACCESS_READ);
+    if (!in_bounds(addr, len)) {
+      log_message(RVV_LOG_ERROR,
+                  "Out of bounds in mmio_load: addr = %lu, len = %lu", addr,
+                  len);
       return false;
     }
     memcpy(bytes, mem + addr - START_MEM, len);
     return true;
   }
   virtual bool mmio_store(reg_t addr, size_t len, const uint8_t *bytes) {
-    if ((addr + len) > (mem_size + START_MEM) || addr < START_MEM) {
-      fprintf(stderr, "Out of bounds in mmio_store: addr = %lu, len = %lu\n",
-              addr, len);
+    watch(addr, len, RVV_ACCESS_WRITE);
+    if (!in_bounds(addr, len)) {
+      log_message(RVV_LOG_ERROR,
+                  "Out of bounds in mmio_store: addr = %lu, len = %lu", addr,
+                  len);
       return false;
     }
     memcpy(mem + addr - START_MEM, bytes, len);
+    dirty_start = std::min(dirty_start, addr - START_MEM);
+    dirty_end = std::max(dirty_end, addr - START_MEM + len);
 
     return true;
   }
+  // Zero everything written since the last call, returns the number of bytes
+  // cleared.
+  uint64_t clear_dirty() {
+    if (dirty_start >= dirty_end) {
+      return 0;
+    }
+    uint64_t len = dirty_end - dirty_start;
+    memset(mem + dirty_start, 0, len);
+    dirty_start = mem_size;
+    dirty_end = 0;
+    return len;
+  }
+  // Read the instruction at addr, without checking watchpoints.
+  bool fetch(reg_t addr, uint64_t *insn) {
+    uint16_t parcel;
+    if (!in_bounds(addr, sizeof(parcel))) {
+      return false;
+    }
+    memcpy(&parcel, mem + addr - START_MEM, sizeof(parcel));
+    size_t len = insn_length(parcel);
+    if (!in_bounds(addr, len)) {
+      return false;
+    }
+    *insn = 0;
+    memcpy(insn, mem + addr - START_MEM, len);
+    return true;
+  }
   virtual void proc_reset(unsigned id) {}
   virtual const char *get_symbol(uint64_t addr) { return NULL; }
+  uint64_t size() const { return mem_size; }
+
+  std::vector<watchpoint_t> watchpoints;
+  // Watchpoints are only checked while running, so that rvv_load_mem and
+  // rvv_store_mem don't hit them.
+  bool watching;
+  // The first access to hit a watchpoint, hit.access is 0 if none did.
+  watchpoint_t hit;
 
 private:
+  // Without computing addr + len, which wraps around for addresses near the
+  // top, e.g. a pc of 0xfffffffffffffffe.
+  bool in_bounds(reg_t addr, size_t len) {
+    return addr >= START_MEM && len <= mem_size &&
+           addr - START_MEM <= mem_size - len;
+  }
+  void watch(reg_t addr, size_t len, int32_t access) {
+    if (!watching || hit.access) {
+      return;
+    }
+    // Clamped, the access may be out of bounds and wrap around.
+    reg_t end = addr + len < addr ? UINT64_MAX : addr + len;
+    for (auto &w : watchpoints) {
+      if ((w.access & access) && addr < w.end && end > w.start) {
+        hit = {addr, end, access};
+        return;
+      }
+    }
+  }
+
   uint8_t *mem;
   uint64_t mem_size;
+  // The range of offsets written to, empty if dirty_start >= dirty_end.
+  uint64_t dirty_start;
+  uint64_t dirty_end;
 };
 
-uint64_t rvv_new_processor(uint32_t vlen, uint32_t elen, uint64_t mem_size) {
+// A page holding the instruction to execute, mapped at every address.
+// processor_t only decodes instructions as its mmu_t fetches them, so an
+// instruction that isn't in memory is fetched from here by a second mmu_t.
+class insn_page : public simif_t {
+public:
+  insn_page() { memset(page, 0, sizeof(page)); }
+  // Place insn at pc's offset, the bytes past the end of the page also at
+  // its start, where the fetch of the next page finds them.
+  void place(reg_t pc, uint64_t insn) {
+    size_t offset = pc % PGSIZE;
+    for (size_t i = 0; i < sizeof(insn); i++) {
+      page[offset + i] = (char)(insn >> (8 * i));
+      if (offset + i >= PGSIZE) {
+        page[offset + i - PGSIZE] = page[offset + i];
+      }
+    }
+  }
+  virtual char *addr_to_mem(reg_t addr) { return page + addr % PGSIZE; }
+  virtual bool mmio_load(reg_t addr, size_t len, uint8_t *bytes) {
+    return false;
+  }
+  virtual bool mmio_store(reg_t addr, size_t len, const uint8_t *bytes) {
+    return false;
+  }
+  virtual void proc_reset(unsigned id) {}
+  virtual const char *get_symbol(uint64_t addr) { return NULL; }
+
+private:
+  // Room for an instruction at the last offset.
+  char page[PGSIZE + sizeof(uint64_t)];
+};
+
+// Handles returned by rvv_new_processor point at a spike_t. Memory is reached
+// through the simif_t we own, architectural state through get_state() and
+// instructions are decoded by fetching them through mmu_t::load_insn, so only
+// public members of processor_t are used.
+struct spike_t {
+  processor_t *proc;
   memory *mem;
-  if (mem_size > 0) {
-    mem = new memory(mem_size);
-  } else {
-    mem = NULL;
+  // Fetches from `page`, never from memory.
+  insn_page page;
+  mmu_t *fetcher;
+  log_buf buf;
+  std::ostream log;
+  // Instructions retired since the last reset and, if coverage is enabled,
+  // how often each encoding retired under each vtype.
+  uint64_t retired;
+  bool coverage;
+  std::map<std::pair<uint64_t, reg_t>, uint64_t> executed;
+  // Checked by rvv_run, cleared by resets.
+  std::set<reg_t> breakpoints;
+  std::vector<std::pair<int32_t, uint64_t>> conditions;
+
+  spike_t(memory *mem)
+      : proc(NULL), mem(mem), fetcher(NULL), log(&buf), retired(0),
+        coverage(false) {}
+  ~spike_t() {
+    delete fetcher;
+    delete proc;
+    delete mem;
   }
+};
+
+static spike_t *get_spike(uint64_t processor) { return (spike_t *)processor; }
 
-  char buf[32] = {0};
-  snprintf(buf, sizeof(buf), "vlen:%u,elen:%u", vlen, elen);
-  isa_parser_t isa("RV64GCV", "MSU");
-  processor_t *proc =
-      new processor_t(isa, buf, mem, 0, false, NULL, std::cerr);
-  reg_t val = proc->state.sstatus->read();
-  proc->state.sstatus->write(val | SSTATUS_VS);
+static processor_t *get_proc(uint64_t processor) {
+  return ((spike_t *)processor)->proc;
+}
+
+static memory *get_mem(uint64_t processor) {
+  return ((spike_t *)processor)->mem;
+}
+
+// State on top of Spike's reset values, shared by new and reset processors.
+static void init_processor(processor_t *proc) {
+  state_t *state = proc->get_state();
+  state->sstatus->write(state->sstatus->read() | SSTATUS_VS);
   proc->VU.vxrm->write(0x02);
-  return (uint64_t)proc;
+}
+
+// processor_t aborts on a --varch it doesn't accept, check for the same
+// things first.
+static const char *check_varch(uint32_t vlen, uint32_t elen) {
+  if (vlen == 0 || (vlen & (vlen - 1)) != 0) {
+    return "vlen must be a power of two";
+  }
+  if (elen == 0 || (elen & (elen - 1)) != 0) {
+    return "elen must be a power of two";
+  }
+  if (vlen < elen) {
+    return "vlen must be >= elen";
+  }
+  return NULL;
+}
+
+int32_t rvv_new_processor(uint32_t vlen, uint32_t elen, uint64_t mem_size,
+                          uint64_t *processor) {
+  return rvv_new_processor_isa("RV64GCV", vlen, elen, mem_size, processor);
+}
+
+int32_t rvv_new_processor_isa(const char *isa_string, uint32_t vlen,
+                              uint32_t elen, uint64_t mem_size,
+                              uint64_t *processor) {
+  const char *invalid = check_varch(vlen, elen);
+  if (invalid) {
+    last_error = invalid;
+    return RVV_ERROR_INVALID_CONFIG;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    std::unique_ptr<memory> mem(mem_size > 0 ? new memory(mem_size) : NULL);
+    char buf[32] = {0};
+    snprintf(buf, sizeof(buf), "vlen:%u,elen:%u", vlen, elen);
+    isa_parser_t isa(isa_string, "MSU");
+    std::unique_ptr<spike_t> s(new spike_t(mem.release()));
+    s->proc = new processor_t(isa, buf, s->mem, 0, false, NULL, s->log);
+    s->fetcher = new mmu_t(&s->page, s->proc);
+    init_processor(s->proc);
+    *processor = (uint64_t)s.release();
+    return 0;
+  });
+}
+
+uint64_t rvv_reset_processor(uint64_t processor) {
+  processor_t *proc = get_proc(processor);
+  memory *mem = get_mem(processor);
+  spike_t *s = get_spike(processor);
+  return guard((uint64_t)0, [&] {
+    proc->reset();
+    init_processor(proc);
+    // Logging stays enabled, but the last instruction's effects are gone.
+    state_t *state = proc->get_state();
+    state->log_reg_write.clear();
+    state->log_mem_read.clear();
+    state->log_mem_write.clear();
+    s->retired = 0;
+    s->executed.clear();
+    s->breakpoints.clear();
+    s->conditions.clear();
+    if (mem) {
+      mem->watchpoints.clear();
+    }
+    return mem ? mem->clear_dirty() : 0;
+  });
+}
+
+// Execute a single instruction at pc, counting it if it retires. Returns the
+// next pc as Spike's instructions do.
+static reg_t execute_insn(spike_t *s, uint64_t insn, reg_t pc) {
+  processor_t *proc = s->proc;
+  state_t *state = proc->get_state();
+  reg_t vtype = s->coverage ? proc->VU.vtype->read() : 0;
+  if (proc->get_log_commits_enabled()) {
+    state->log_reg_write.clear();
+    state->log_mem_read.clear();
+    state->log_mem_write.clear();
+  }
+  // The icache is tagged by address, not by contents.
+  s->page.place(pc, insn);
+  s->fetcher->flush_icache();
+  insn_fetch_t fetch = s->fetcher->load_insn(pc);
+  reg_t npc = fetch.func(proc, fetch.insn, pc);
+  if (npc == PC_SERIALIZE_BEFORE) {
+    state->serialized = true;
+    npc = fetch.func(proc, fetch.insn, pc);
+  }
+  s->retired++;
+  if (s->coverage) {
+    s->executed[std::make_pair(insn, vtype)]++;
+  }
+  return npc;
 }
 
 int32_t rvv_execute(uint64_t processor, uint64_t instruction) {
-  processor_t *proc = (processor_t *)processor;
+  spike_t *s = get_spike(processor);
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    try {
+      execute_insn(s, instruction, 0);
+    } catch (trap_t &e) {
+      // `cause` is starting from zero, see `CAUSE_MISALIGNED_FETCH`
+      return (int)e.cause() + 1;
+    }
+    return 0;
+  });
+}
+
+int32_t rvv_execute_batch(uint64_t processor, const uint32_t *insns,
+                          uint64_t count, uint64_t *retired) {
+  spike_t *s = get_spike(processor);
+  *retired = 0;
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    try {
+      for (uint64_t i = 0; i < count; i++) {
+        execute_insn(s, insns[i], 0);
+        *retired = i + 1;
+      }
+    } catch (trap_t &e) {
+      return (int)e.cause() + 1;
+    }
+    return 0;
+  });
+}
+
+void rvv_set_coverage(uint64_t processor, int32_t enabled) {
+  get_spike(processor)->coverage = enabled != 0;
+}
+
+uint64_t rvv_get_retired(uint64_t processor) {
+  return get_spike(processor)->retired;
+}
+
+uint64_t rvv_get_coverage(uint64_t processor, uint64_t *insns, uint64_t *vtypes,
+                          uint64_t *counts, uint64_t capacity) {
+  spike_t *s = get_spike(processor);
+  uint64_t i = 0;
+  for (auto &entry : s->executed) {
+    if (i < capacity) {
+      insns[i] = entry.first.first;
+      vtypes[i] = entry.first.second;
+      counts[i] = entry.second;
+    }
+    i++;
+  }
+  return i;
+}
+
+void rvv_enable_commit_log(uint64_t processor) {
+  guard([&] { get_proc(processor)->enable_log_commits(); });
+}
+
+int32_t rvv_commit_log_enabled(uint64_t processor) {
+  return get_proc(processor)->get_log_commits_enabled() ? 1 : 0;
+}
+
+uint64_t rvv_get_effects(uint64_t processor, rvv_effect_t *effects,
+                         uint64_t capacity) {
+  state_t *state = get_proc(processor)->get_state();
+  uint64_t i = 0;
+  auto add = [&](rvv_effect_t effect) {
+    if (i < capacity) {
+      effects[i] = effect;
+    }
+    i++;
+  };
+  for (auto &write : state->log_reg_write) {
+    // The low bits of the key are the register type, see WRITE_REG and
+    // friends in decode_macros.h.
+    uint64_t reg = write.first >> 4;
+    uint64_t value = write.second.v[0];
+    switch (write.first & 0xf) {
+    case 0:
+      add({RVV_EFFECT_XREG, reg, 0, value, 8});
+      break;
+    case 1:
+      add({RVV_EFFECT_FREG, reg, 0, value, 8});
+      break;
+    case 2:
+      add({RVV_EFFECT_VREG, reg, 0, 0, 0});
+      break;
+    case 4:
+      add({RVV_EFFECT_CSR, reg, 0, value, 8});
+      break;
+    }
+  }
+  for (auto &read : state->log_mem_read) {
+    add({RVV_EFFECT_LOAD, 0, std::get<0>(read), 0, std::get<2>(read)});
+  }
+  for (auto &write : state->log_mem_write) {
+    add({RVV_EFFECT_STORE, 0, std::get<0>(write), std::get<1>(write),
+         std::get<2>(write)});
+  }
+  return i;
+}
+
+uint64_t rvv_get_pc(uint64_t processor) {
+  return get_proc(processor)->get_state()->pc;
+}
+
+void rvv_set_pc(uint64_t processor, uint64_t pc) {
+  get_proc(processor)->get_state()->pc = pc;
+}
+
+void rvv_add_breakpoint(uint64_t processor, uint64_t pc) {
+  guard([&] { get_spike(processor)->breakpoints.insert(pc); });
+}
+
+int32_t rvv_remove_breakpoint(uint64_t processor, uint64_t pc) {
+  return get_spike(processor)->breakpoints.erase(pc) ? 0 : -1;
+}
+
+int32_t rvv_add_watchpoint(uint64_t processor, uint64_t start, uint64_t end,
+                           int32_t access) {
+  memory *mem = get_mem(processor);
+  if (!mem) {
+    return -1;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    mem->watchpoints.push_back({start, end, access});
+    return 0;
+  });
+}
+
+int32_t rvv_remove_watchpoint(uint64_t processor, uint64_t start, uint64_t end,
+                              int32_t access) {
+  memory *mem = get_mem(processor);
+  if (!mem) {
+    return -1;
+  }
+  auto &w = mem->watchpoints;
+  for (auto it = w.begin(); it != w.end(); it++) {
+    if (it->start == start && it->end == end && it->access == access) {
+      w.erase(it);
+      return 0;
+    }
+  }
+  return -1;
+}
+
+void rvv_add_condition(uint64_t processor, int32_t condition, uint64_t arg) {
+  spike_t *s = get_spike(processor);
+  guard([&] {
+    // Register writes are only recorded with commit logging.
+    if (condition == RVV_COND_XREG_WRITTEN ||
+        condition == RVV_COND_VREG_WRITTEN) {
+      s->proc->enable_log_commits();
+    }
+    s->conditions.push_back(std::make_pair(condition, arg));
+  });
+}
+
+int32_t rvv_remove_condition(uint64_t processor, int32_t condition,
+                             uint64_t arg) {
+  auto &c = get_spike(processor)->conditions;
+  auto it = std::find(c.begin(), c.end(), std::make_pair(condition, arg));
+  if (it == c.end()) {
+    return -1;
+  }
+  c.erase(it);
+  return 0;
+}
+
+// Whether `condition` holds after an instruction that started with vl and
+// vtype.
+static bool condition_met(processor_t *proc,
+                          const std::pair<int32_t, uint64_t> &condition,
+                          reg_t vl, reg_t vtype) {
+  auto &writes = proc->get_state()->log_reg_write;
+  switch (condition.first) {
+  case RVV_COND_VL_CHANGED:
+    return proc->VU.vl->read() != vl;
+  case RVV_COND_VTYPE_CHANGED:
+    return proc->VU.vtype->read() != vtype;
+  case RVV_COND_XREG_WRITTEN:
+    return writes.count(condition.second << 4) != 0;
+  case RVV_COND_VREG_WRITTEN:
+    return writes.count(condition.second << 4 | 2) != 0;
+  }
+  return false;
+}
+
+// Execute the instruction at the current pc. Returns whether to stop, with
+// the reason in `stop`.
+static bool step(spike_t *s, rvv_stop_t *stop) {
+  processor_t *proc = s->proc;
+  state_t *state = proc->get_state();
+  reg_t pc = state->pc;
+  reg_t vl = proc->VU.vl->read();
+  reg_t vtype = proc->VU.vtype->read();
+  stop->pc = pc;
+  uint64_t insn;
+  if (!s->mem || !s->mem->fetch(pc, &insn)) {
+    stop->reason = RVV_STOP_TRAP;
+    stop->error = CAUSE_FETCH_ACCESS + 1;
+    return true;
+  }
+  s->mem->hit.access = 0;
   try {
-    insn_func_t func = proc->decode_insn(instruction);
-    func(proc, instruction, 0);
+    reg_t npc = execute_insn(s, insn, pc);
+    // Serializing instructions, e.g. CSR writes, set the pc themselves and
+    // return an odd marker.
+    if ((npc & 1) == 0) {
+      state->pc = npc;
+    }
   } catch (trap_t &e) {
-    // fprintf(stderr, "Exception found, error code: %lu(%s), instruction:
-    // 0x%08lX)\n", e.cause(), e.name(), e.get_tval()); `cause` is starting from
-    // zero, see `CAUSE_MISALIGNED_FETCH`
-    return (int)e.cause() + 1;
+    stop->reason = RVV_STOP_TRAP;
+    stop->error = (int)e.cause() + 1;
+    return true;
   }
-  return 0;
+  watchpoint_t &hit = s->mem->hit;
+  if (hit.access) {
+    stop->reason = RVV_STOP_WATCHPOINT;
+    stop->addr = hit.start;
+    stop->len = hit.end - hit.start;
+    stop->access = hit.access;
+    return true;
+  }
+  for (auto &condition : s->conditions) {
+    if (condition_met(proc, condition, vl, vtype)) {
+      stop->reason = RVV_STOP_CONDITION;
+      stop->condition = condition.first;
+      stop->arg = condition.second;
+      return true;
+    }
+  }
+  return false;
+}
+
+int32_t rvv_run(uint64_t processor, uint64_t max_insns, rvv_stop_t *stop) {
+  spike_t *s = get_spike(processor);
+  memset(stop, 0, sizeof(*stop));
+  if (s->mem) {
+    s->mem->watching = true;
+  }
+  int32_t r = guard(RVV_ERROR_EXCEPTION, [&] {
+    for (uint64_t i = 0; i < max_insns; i++) {
+      // A breakpoint at the pc run starts from has already been reported.
+      reg_t pc = s->proc->get_state()->pc;
+      if (i > 0 && s->breakpoints.count(pc)) {
+        stop->reason = RVV_STOP_BREAKPOINT;
+        stop->pc = pc;
+        return 0;
+      }
+      if (step(s, stop)) {
+        return 0;
+      }
+    }
+    stop->reason = RVV_STOP_LIMIT;
+    stop->pc = s->proc->get_state()->pc;
+    return 0;
+  });
+  if (s->mem) {
+    s->mem->watching = false;
+  }
+  return r;
 }
 
 int32_t rvv_get_vreg(uint64_t processor, uint64_t offset, uint8_t *mem,
                      uint64_t mem_size) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   uint8_t *reg_file = (uint8_t *)proc->VU.reg_file;
   uint64_t total_size = NVPR * proc->VU.vlenb;
-  if ((offset + mem_size) > total_size) {
-    fprintf(stderr,
-            "out of bounds: offset = %lu, mem_size = %lu, total_size = %lu\n",
-            offset, mem_size, total_size);
+  if (offset > total_size || mem_size > total_size - offset) {
+    log_message(RVV_LOG_ERROR,
+                "out of bounds: offset = %lu, mem_size = %lu, total_size = %lu",
+                offset, mem_size, total_size);
     return -1;
   }
   memcpy(mem, reg_file + offset, mem_size);
@@ -95,15 +654,15 @@ int32_t rvv_get_vreg(uint64_t processor, uint64_t offset, uint8_t *mem,
   return 0;
 }
 
-int32_t rvv_set_vreg(uint64_t processor, uint64_t offset, uint8_t *mem,
+int32_t rvv_set_vreg(uint64_t processor, uint64_t offset, const uint8_t *mem,
                      uint64_t mem_size) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   uint8_t *reg_file = (uint8_t *)proc->VU.reg_file;
   uint64_t total_size = NVPR * proc->VU.vlenb;
-  if ((offset + mem_size) > total_size) {
-    fprintf(stderr,
-            "out of bounds: offset = %lu, mem_size = %lu, total_size = %lu\n",
-            offset, mem_size, total_size);
+  if (offset > total_size || mem_size > total_size - offset) {
+    log_message(RVV_LOG_ERROR,
+                "out of bounds: offset = %lu, mem_size = %lu, total_size = %lu",
+                offset, mem_size, total_size);
     return -1;
   }
   memcpy(reg_file + offset, mem, mem_size);
@@ -112,74 +671,133 @@ int32_t rvv_set_vreg(uint64_t processor, uint64_t offset, uint8_t *mem,
 }
 
 int32_t rvv_get_xreg(uint64_t processor, uint64_t index, uint64_t *content) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   if (index >= NXPR) {
-    fprintf(stderr, "error, out of bounds: %lu >= NXPR\n", index);
+    log_message(RVV_LOG_ERROR, "error, out of bounds: %lu >= NXPR", index);
     return -1;
   }
-  *content = proc->state.XPR[index];
+  *content = proc->get_state()->XPR[index];
   return 0;
 }
 
 int32_t rvv_set_xreg(uint64_t processor, uint64_t index, uint64_t content) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   if (index >= NXPR) {
-    fprintf(stderr, "error, out of bounds: %lu >= NXPR\n", index);
+    log_message(RVV_LOG_ERROR, "error, out of bounds: %lu >= NXPR", index);
     return -1;
   }
-  proc->state.XPR.write(index, content);
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    proc->get_state()->XPR.write(index, content);
+    return 0;
+  });
+}
+
+int32_t rvv_get_freg(uint64_t processor, uint64_t index, uint64_t *content) {
+  processor_t *proc = get_proc(processor);
+  if (index >= NFPR) {
+    log_message(RVV_LOG_ERROR, "error, out of bounds: %lu >= NFPR", index);
+    return -1;
+  }
+  *content = proc->get_state()->FPR[index].v[0];
   return 0;
 }
 
+int32_t rvv_set_freg(uint64_t processor, uint64_t index, uint64_t content) {
+  processor_t *proc = get_proc(processor);
+  if (index >= NFPR) {
+    log_message(RVV_LOG_ERROR, "error, out of bounds: %lu >= NFPR", index);
+    return -1;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    // NaN-boxed, as a double written by fld
+    freg_t boxed;
+    boxed.v[0] = content;
+    boxed.v[1] = UINT64_MAX;
+    proc->get_state()->FPR.write(index, boxed);
+    return 0;
+  });
+}
+
+int32_t rvv_get_csr(uint64_t processor, uint64_t which, uint64_t *content) {
+  processor_t *proc = get_proc(processor);
+  if (which >= 4096) {
+    log_message(RVV_LOG_ERROR, "error, out of bounds: csr %lu >= 4096", which);
+    return -1;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    try {
+      *content = proc->get_csr(which);
+    } catch (trap_t &e) {
+      return (int)e.cause() + 1;
+    }
+    return 0;
+  });
+}
+
+int32_t rvv_set_csr(uint64_t processor, uint64_t which, uint64_t content) {
+  processor_t *proc = get_proc(processor);
+  if (which >= 4096) {
+    log_message(RVV_LOG_ERROR, "error, out of bounds: csr %lu >= 4096", which);
+    return -1;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    try {
+      proc->put_csr(which, content);
+    } catch (trap_t &e) {
+      return (int)e.cause() + 1;
+    }
+    return 0;
+  });
+}
+
 uint64_t rvv_get_vlen(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   return proc->VU.VLEN;
 }
 
 uint64_t rvv_get_elen(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   return proc->VU.ELEN;
 }
 
 uint64_t rvv_get_vl(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
-  return proc->VU.vl->read();
+  processor_t *proc = get_proc(processor);
+  return guard((uint64_t)0, [&] { return proc->VU.vl->read(); });
 }
 
 uint64_t rvv_get_sew(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   return proc->VU.vsew;
 }
 
 uint64_t rvv_get_vtype(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
-  return proc->VU.vtype->read();
+  processor_t *proc = get_proc(processor);
+  return guard((uint64_t)0, [&] { return proc->VU.vtype->read(); });
 }
 
 float rvv_get_lmul(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   return proc->VU.vflmul;
 }
 
 uint64_t rvv_get_vill(uint64_t processor) {
-  processor_t *proc = (processor_t *)processor;
+  processor_t *proc = get_proc(processor);
   return proc->VU.vill;
 }
 
 void rvv_delete_processor(uint64_t h) {
-  processor_t *p = (processor_t *)h;
-  delete static_cast<memory *>(p->sim);
-  delete p;
+  guard([&] { delete (spike_t *)h; });
 }
 
-int rvv_load_mem(uint64_t processor, uint64_t addr, uint64_t len,
-                 uint8_t *bytes) {
-  processor_t *proc = (processor_t *)processor;
-  mmu_t *mmu = proc->get_mmu();
+int32_t rvv_load_mem(uint64_t processor, uint64_t addr, uint64_t len,
+                     uint8_t *bytes) {
+  memory *mem = get_mem(processor);
   if (addr < START_MEM) {
     return -4;
   }
-  bool success = mmu->mmio_load(addr, len, bytes);
+  bool success = guard(false, [&] {
+    return mem && mem->mmio_load(addr, len, bytes);
+  });
   if (success) {
     return 0;
   } else {
@@ -187,14 +805,15 @@ int rvv_load_mem(uint64_t processor, uint64_t addr, uint64_t len,
   }
 }
 
-int rvv_store_mem(uint64_t processor, uint64_t addr, uint64_t len,
-                  uint8_t *bytes) {
-  processor_t *proc = (processor_t *)processor;
+int32_t rvv_store_mem(uint64_t processor, uint64_t addr, uint64_t len,
+                      const uint8_t *bytes) {
+  memory *mem = get_mem(processor);
   if (addr < START_MEM) {
     return -4;
   }
-  mmu_t *mmu = proc->get_mmu();
-  bool success = mmu->mmio_store(addr, len, bytes);
+  bool success = guard(false, [&] {
+    return mem && mem->mmio_store(addr, len, bytes);
+  });
   if (success) {
     return 0;
   } else {
@@ -202,22 +821,105 @@ int rvv_store_mem(uint64_t processor, uint64_t addr, uint64_t len,
   }
 }
 
-uint64_t rvv_new_disassembler(uint32_t xlen) {
-  isa_parser_t isa("RV64GCV", "MSU");
-  disassembler_t *dis = new disassembler_t(&isa);
-  return (uint64_t)dis;
+uint64_t rvv_get_mem_size(uint64_t processor) {
+  memory *mem = get_mem(processor);
+  return mem ? mem->size() : 0;
 }
 
-int rvv_disassemble(uint64_t dis, uint64_t inst, char *output,
-                    uint32_t *output_len) {
+const char *rvv_spike_version(void) { return PACKAGE_VERSION; }
+
+int32_t rvv_new_disassembler(uint32_t xlen, uint64_t *dis) {
+  if (xlen != 32 && xlen != 64) {
+    last_error = "xlen must be 32 or 64";
+    return RVV_ERROR_INVALID_CONFIG;
+  }
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    isa_parser_t isa(xlen == 32 ? "RV32GCV" : "RV64GCV", "MSU");
+    *dis = (uint64_t) new disassembler_t(&isa);
+    return 0;
+  });
+}
+
+int32_t rvv_disassemble(uint64_t dis, uint64_t inst, char *output,
+                        uint32_t *output_len) {
   disassembler_t *disassembler = (disassembler_t *)dis;
-  std::string str = disassembler->disassemble((insn_t)inst);
-  if (str.length() > (*output_len - 1)) {
-    return -4;
+  return guard(RVV_ERROR_EXCEPTION, [&] {
+    std::string str = disassembler->disassemble((insn_t)inst);
+    if (str.length() > (*output_len - 1)) {
+      return -4;
+    }
+    strncpy(output, str.c_str(), str.length() + 1);
+    *output_len = str.length();
+    return 0;
+  });
+}
+
+void rvv_delete_disassembler(uint64_t dis) {
+  guard([&] { delete (disassembler_t *)dis; });
+}
+
+struct opcode_t {
+  const char *name;
+  uint32_t match;
+  uint32_t mask;
+};
+
+static const opcode_t opcodes[] = {
+#define DECLARE_INSN(name, match, mask) {#name, match, mask},
+#include "encoding.h"
+#undef DECLARE_INSN
+};
+
+uint32_t rvv_opcode_count(void) {
+  return sizeof(opcodes) / sizeof(opcodes[0]);
+}
+
+int32_t rvv_opcode(uint32_t index, const char **name, uint32_t *match,
+                   uint32_t *mask) {
+  if (index >= rvv_opcode_count()) {
+    return -1;
   }
-  strncpy(output, str.c_str(), str.length() + 1);
-  *output_len = str.length();
+  *name = opcodes[index].name;
+  *match = opcodes[index].match;
+  *mask = opcodes[index].mask;
   return 0;
 }
 
-void rvv_delete_disassembler(uint64_t dis) { delete (disassembler_t *)dis; }
+// Entries only valid on RV32, which overlap RV64 instructions with as many
+// fixed bits, e.g. slli_rv32 and slli, or c_flw and c_ld.
+static bool rv32_only(const char *name) {
+  static const char *const compressed[] = {"c_flw", "c_flwsp", "c_fsw",
+                                           "c_fswsp", "c_jal"};
+  size_t len = strlen(name);
+  if (len > 5 && strcmp(name + len - 5, "_rv32") == 0) {
+    return true;
+  }
+  for (const char *c : compressed) {
+    if (strcmp(name, c) == 0) {
+      return true;
+    }
+  }
+  return false;
+}
+
+int32_t rvv_decode(uint64_t inst, uint32_t *index, uint32_t *length) {
+  insn_t insn(inst);
+  int32_t found = -1;
+  int best = -1;
+  // Pseudo-instructions like c.nop overlap more general entries, prefer the
+  // entry with the most fixed bits.
+  for (uint32_t i = 0; i < rvv_opcode_count(); i++) {
+    if ((insn.bits() & opcodes[i].mask) == opcodes[i].match &&
+        __builtin_popcount(opcodes[i].mask) > best &&
+        !rv32_only(opcodes[i].name)) {
+      best = __builtin_popcount(opcodes[i].mask);
+      found = i;
+    }
+  }
+  if (found < 0) {
+    return -1;
+  }
+  *index = found;
+  *length = insn.length();
+  return 0;
+}
@@ -7,24 +7,195 @@
 extern "C" {
 #endif
 
+/*
+ * No C++ exception escapes the functions below. Those returning int32_t return RVV_ERROR_EXCEPTION
+ * if one is caught, the others return 0; either way the message is kept for rvv_last_error.
+ */
+#define RVV_ERROR_INVALID_CONFIG -5
+#define RVV_ERROR_EXCEPTION -6
+
+/*
+ * The message of the last RVV_ERROR_INVALID_CONFIG or RVV_ERROR_EXCEPTION on the calling thread.
+ * Valid until the next such error on that thread.
+ */
+const char *rvv_last_error(void);
+
+#define RVV_LOG_ERROR 1
+#define RVV_LOG_WARN 2
+#define RVV_LOG_INFO 3
+
+/*
+ * Diagnostics from the shim and from Spike's log stream are passed to the callback, one line at a
+ * time without the newline. They are printed to stderr until a callback is set.
+ * level: one of RVV_LOG_*
+ */
+typedef void (*rvv_log_callback)(int32_t level, const char *message);
+void rvv_set_log_callback(rvv_log_callback callback);
+
 /*
  * vlen: Vector register size in bits, set it 128 by default
  * elen: Vector element size in bits, set it 64 by default
  * mem_size: if 0, disable memory; otherwise, set memory available in range [4096, 4096 + mem_size]
+ * processor: set to the new processor on success
+ *
+ * return: 0, success; RVV_ERROR_INVALID_CONFIG if vlen or elen isn't a power of two or vlen < elen
+ */
+int32_t rvv_new_processor(uint32_t vlen, uint32_t elen, uint64_t mem_size, uint64_t *processor);
+/*
+ * Like rvv_new_processor with an ISA string other than RV64GCV, e.g. RV64IMACV.
+ * return: as rvv_new_processor; RVV_ERROR_EXCEPTION if Spike rejects the ISA string
+ */
+int32_t rvv_new_processor_isa(const char *isa, uint32_t vlen, uint32_t elen, uint64_t mem_size,
+                              uint64_t *processor);
+/*
+ * Reset to the state of a new processor: registers zeroed, CSRs at their reset values and memory
+ * written since the last reset cleared.
+ * return: the number of bytes of memory cleared
  */
-uint64_t rvv_new_processor(uint32_t vlen, uint32_t elen, uint64_t mem_size);
+uint64_t rvv_reset_processor(uint64_t processor);
 int32_t rvv_execute(uint64_t processor, uint64_t instruction);
+/*
+ * Execute `count` instructions in order, stopping at the first one that traps.
+ * retired: the number of instructions executed without trapping
+ * return: 0 if all were executed, otherwise as rvv_execute for the instruction at index `retired`
+ */
+int32_t rvv_execute_batch(uint64_t processor, const uint32_t *insns, uint64_t count, uint64_t *retired);
+/*
+ * Count how often each encoding retires under each vtype, off for new processors. The counts and
+ * the number of retired instructions are cleared by rvv_reset_processor; the setting is kept.
+ */
+void rvv_set_coverage(uint64_t processor, int32_t enabled);
+/*
+ * The number of instructions retired since the processor was created or last reset.
+ */
+uint64_t rvv_get_retired(uint64_t processor);
+/*
+ * Copy up to `capacity` (encoding, vtype, count) entries of the coverage counts, ordered by
+ * encoding then vtype. vtype is the value before the instruction executed.
+ * return: the total number of entries
+ */
+uint64_t rvv_get_coverage(uint64_t processor, uint64_t *insns, uint64_t *vtypes, uint64_t *counts,
+                          uint64_t capacity);
+/*
+ * What the last instruction executed wrote and accessed, as in Spike's commit log. Recording
+ * starts with rvv_enable_commit_log, or rvv_add_condition for register writes, which slows
+ * execution down and can't be turned off, not even by rvv_reset_processor.
+ */
+#define RVV_EFFECT_XREG 1
+#define RVV_EFFECT_FREG 2
+#define RVV_EFFECT_VREG 3
+#define RVV_EFFECT_CSR 4
+#define RVV_EFFECT_LOAD 5
+#define RVV_EFFECT_STORE 6
+
+typedef struct {
+  int32_t kind;
+  /* registers: the register or CSR number */
+  uint64_t reg;
+  /* loads and stores */
+  uint64_t addr;
+  /* the value written, except for vector registers; the low 64 bits of f registers */
+  uint64_t value;
+  /* in bytes */
+  uint64_t size;
+} rvv_effect_t;
+
+void rvv_enable_commit_log(uint64_t processor);
+/* return: 1 if commit logging is enabled, otherwise 0 */
+int32_t rvv_commit_log_enabled(uint64_t processor);
+/*
+ * Copy up to `capacity` effects, register writes first.
+ * return: the total number of effects
+ */
+uint64_t rvv_get_effects(uint64_t processor, rvv_effect_t *effects, uint64_t capacity);
+/*
+ * Running from memory. rvv_run fetches instructions from memory at pc (4096 after a reset) and
+ * executes them until a stop condition, a trap or `max_insns` instructions. Traps aren't taken, pc
+ * stays at the trapping instruction. Breakpoints, watchpoints and conditions are cleared by
+ * rvv_reset_processor.
+ */
+#define RVV_ACCESS_READ 1
+#define RVV_ACCESS_WRITE 2
+
+#define RVV_COND_VL_CHANGED 1
+#define RVV_COND_VTYPE_CHANGED 2
+/* arg: the register number */
+#define RVV_COND_XREG_WRITTEN 3
+#define RVV_COND_VREG_WRITTEN 4
+
+#define RVV_STOP_LIMIT 0
+/* pc is at a breakpoint, the instruction there hasn't executed */
+#define RVV_STOP_BREAKPOINT 1
+/* the instruction at pc accessed a watched range */
+#define RVV_STOP_WATCHPOINT 2
+/* the instruction at pc met a condition */
+#define RVV_STOP_CONDITION 3
+/* the instruction at pc trapped */
+#define RVV_STOP_TRAP 4
+
+typedef struct {
+  int32_t reason;
+  /* RVV_STOP_TRAP: as returned by rvv_execute */
+  int32_t error;
+  uint64_t pc;
+  /* RVV_STOP_CONDITION: the condition met and its argument */
+  int32_t condition;
+  uint64_t arg;
+  /* RVV_STOP_WATCHPOINT: the first access that hit a watchpoint, RVV_ACCESS_READ or RVV_ACCESS_WRITE */
+  uint64_t addr;
+  uint64_t len;
+  int32_t access;
+} rvv_stop_t;
+
+uint64_t rvv_get_pc(uint64_t processor);
+void rvv_set_pc(uint64_t processor, uint64_t pc);
+void rvv_add_breakpoint(uint64_t processor, uint64_t pc);
+/* return: 0, success; -1 if there is no such breakpoint. Same for watchpoints and conditions */
+int32_t rvv_remove_breakpoint(uint64_t processor, uint64_t pc);
+/*
+ * Watch accesses by instructions to [start, end).
+ * access: RVV_ACCESS_READ, RVV_ACCESS_WRITE or both
+ * return: 0, success; -1 if the processor has no memory
+ */
+int32_t rvv_add_watchpoint(uint64_t processor, uint64_t start, uint64_t end, int32_t access);
+int32_t rvv_remove_watchpoint(uint64_t processor, uint64_t start, uint64_t end, int32_t access);
+/*
+ * Stop after an instruction meets `condition`, one of RVV_COND_*. Conditions on register writes
+ * enable Spike's commit logging, which slows execution down.
+ */
+void rvv_add_condition(uint64_t processor, int32_t condition, uint64_t arg);
+int32_t rvv_remove_condition(uint64_t processor, int32_t condition, uint64_t arg);
+/*
+ * stop: why execution stopped
+ * return: 0, success; RVV_ERROR_EXCEPTION
+ */
+int32_t rvv_run(uint64_t processor, uint64_t max_insns, rvv_stop_t *stop);
+
 /**
  *  offset: the offset in register file in bytes
  *
  *  return: 0, success; otherwise, failed. Same to other functions
  */
 int32_t rvv_get_vreg(uint64_t processor, uint64_t offset, uint8_t *mem, uint64_t mem_size);
-int32_t rvv_set_vreg(uint64_t processor, uint64_t offset, uint8_t *mem, uint64_t mem_size);
+int32_t rvv_set_vreg(uint64_t processor, uint64_t offset, const uint8_t *mem, uint64_t mem_size);
 
 int32_t rvv_get_xreg(uint64_t processor, uint64_t index, uint64_t *content);
 int32_t rvv_set_xreg(uint64_t processor, uint64_t index, uint64_t content);
 
+/**
+ * index: f0-f31, the low 64 bits. Written values are NaN-boxed.
+ */
+int32_t rvv_get_freg(uint64_t processor, uint64_t index, uint64_t *content);
+int32_t rvv_set_freg(uint64_t processor, uint64_t index, uint64_t content);
+
+/**
+ * which: the CSR number, e.g. 0xc20 for vl
+ * return: 0, success; trap cause + 1 if the processor doesn't have the CSR
+ * or it can't be written, like rvv_execute
+ */
+int32_t rvv_get_csr(uint64_t processor, uint64_t which, uint64_t *content);
+int32_t rvv_set_csr(uint64_t processor, uint64_t which, uint64_t content);
+
 uint64_t rvv_get_vlen(uint64_t processor);
 uint64_t rvv_get_elen(uint64_t processor);
 uint64_t rvv_get_vl(uint64_t processor);
@@ -39,15 +210,43 @@ uint64_t rvv_get_vill(uint64_t processor);
  * bytes: the buffer to hold the content loaded from memory. It should have same length with `len`
  * note: the memory should be in range [4096, 4096+mem_size]. `mem_size` is specified in rvv_new_processor
  */
-int rvv_load_mem(uint64_t processor, uint64_t addr, uint64_t len, uint8_t *bytes);
-int rvv_store_mem(uint64_t processor, uint64_t addr, uint64_t len, uint8_t *bytes);
+int32_t rvv_load_mem(uint64_t processor, uint64_t addr, uint64_t len, uint8_t *bytes);
+int32_t rvv_store_mem(uint64_t processor, uint64_t addr, uint64_t len, const uint8_t *bytes);
+/* return: the `mem_size` given to rvv_new_processor, 0 without memory */
+uint64_t rvv_get_mem_size(uint64_t processor);
 
-uint64_t rvv_new_disassembler(uint32_t xlen);
-int rvv_disassemble(uint64_t dis, uint64_t inst, char *output, uint32_t *output_len);
+/*
+ * xlen: 32 for RV32GCV or 64 for RV64GCV
+ * dis: the new disassembler, on success
+ * return: 0 on success, otherwise RVV_ERROR_INVALID_CONFIG or RVV_ERROR_EXCEPTION
+ */
+int32_t rvv_new_disassembler(uint32_t xlen, uint64_t *dis);
+int32_t rvv_disassemble(uint64_t dis, uint64_t inst, char *output, uint32_t *output_len);
 void rvv_delete_disassembler(uint64_t dis);
 
 void rvv_delete_processor(uint64_t);
 
+/*
+ * The riscv-isa-sim version (PACKAGE_VERSION from its config.h) the shim was built against.
+ */
+const char *rvv_spike_version(void);
+
+/*
+ * Spike's opcode table, generated from riscv/encoding.h.
+ * name: the instruction name with underscores, e.g. "vadd_vv". Points to static storage.
+ */
+uint32_t rvv_opcode_count(void);
+int32_t rvv_opcode(uint32_t index, const char **name, uint32_t *match, uint32_t *mask);
+/**
+ * Find the most specific entry of the opcode table matching `inst` as an RV64 instruction,
+ * skipping RV32-only entries like slli_rv32 and c_flw.
+ * index: index of the entry, to be passed to rvv_opcode
+ * length: instruction length in bytes, as decoded by insn_t
+ *
+ * return: 0, success; -1 if no entry matches
+ */
+int32_t rvv_decode(uint64_t inst, uint32_t *index, uint32_t *length);
+
 #ifdef __cplusplus
 }
 #endif
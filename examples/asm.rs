@@ -0,0 +1,58 @@
+use spike_sys::asm::AsmErrorKind;
+use spike_sys::insn::{Mask, VInsn, VReg};
+use spike_sys::*;
+
+pub fn main() {
+    // The encodings hard-coded in the other examples.
+    assert_eq!(assemble!("vsetivli t0, 8, e64, m1").unwrap(), [0xc18472d7]);
+    assert_eq!(assemble!("vadd.vv v2, v10, v20").unwrap(), [0x02aa0157]);
+    assert_eq!(assemble!("li t0, 4096", "li t1, 17").unwrap(), [0x6285, 0x4345]);
+    assert_eq!(assemble!("sd t1, 0(t0)").unwrap(), [0x0062b023]);
+
+    // The typed builder produces the same encodings.
+    let vadd = VInsn::vadd_vv(VReg::V2, VReg::V10, VReg::V20);
+    assert_eq!(vadd.encode(), 0x02aa0157);
+    assert_eq!(
+        [vadd.masked(Mask::V0).encode()],
+        assemble!("vadd.vv v2, v10, v20, v0.t").unwrap()[..]
+    );
+
+    let spike = Spike::new(128, 64, 4096).unwrap();
+    spike
+        .execute_asm(
+            "
+            vsetivli t0, 2, e64, m1
+            li t0, 4096
+            li t1, 0x123456789abcdef0
+            sd t1, 0(t0)
+            sd t1, 8(t0)
+            vle64.v v10, (t0)
+            vadd.vv v2, v10, v10
+            ",
+        )
+        .unwrap();
+    assert_eq!(spike.get_vl(), 2);
+    assert_eq!(spike.get_xreg(6).unwrap(), 0x123456789abcdef0);
+
+    let mut result = [0u8; 16];
+    let vlenb = spike.get_vlen() / 8;
+    spike.get_vreg(vlenb * 2, &mut result).unwrap();
+    assert_eq!(result[..8], 0x2468acf13579bde0u64.to_le_bytes());
+    assert_eq!(result[8..], 0x2468acf13579bde0u64.to_le_bytes());
+
+    // 0 is defined to be an illegal instruction.
+    let mut insns = assemble!("vsetivli t0, 2, e64, m1", "vadd.vv v2, v10, v10").unwrap();
+    insns.extend_from_slice(&[0, 0x02aa0157]);
+    let r = spike.execute_batch(&insns);
+    assert_eq!(r.retired, 2);
+    assert!(r.trap.is_some());
+    assert!(spike.execute_batch(&insns[..2]).is_ok());
+
+    let err = spike.execute_asm("vfoo.vv v2, v10, v20").unwrap_err();
+    println!("{}", err);
+    let err = assemble!("li t0, 1", "addi t0, t0, 4096").unwrap_err();
+    println!("{}", err);
+    assert_eq!(err.line, 2);
+    assert!(matches!(err.kind, AsmErrorKind::ImmediateRange { .. }));
+    println!("done");
+}
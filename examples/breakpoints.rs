@@ -0,0 +1,89 @@
+use spike_sys::asm::{assemble, to_bytes};
+use spike_sys::config::START_MEM;
+use spike_sys::disasm::insn_length;
+use spike_sys::insn::VReg;
+use spike_sys::run::{Access, Condition, StopReason};
+use spike_sys::Spike;
+
+const DATA: u64 = 8192;
+const CAUSE_MACHINE_ECALL: u64 = 11;
+
+pub fn main() {
+    let insns = assemble(
+        "
+        li a0, 8192
+        vsetivli t1, 2, e64, m1
+        vle64.v v8, (a0)
+        vadd.vv v8, v8, v8
+        vse64.v v8, (a0)
+        vsetivli t1, 1, e64, m1
+        ecall
+        ",
+    )
+    .unwrap();
+    let mut pcs = vec![START_MEM];
+    for insn in &insns {
+        pcs.push(pcs.last().unwrap() + insn_length(*insn as u16) as u64);
+    }
+
+    let spike = Spike::new(128, 64, 8192).unwrap();
+    spike.store_mem(START_MEM, &to_bytes(&insns)).unwrap();
+    spike.store_mem(DATA, &[1; 16]).unwrap();
+    assert_eq!(spike.pc(), START_MEM);
+
+    spike.add_breakpoint(pcs[3]);
+    spike.add_watchpoint(DATA..DATA + 8, Access::Write).unwrap();
+    spike.add_condition(Condition::VlChanged);
+
+    match spike.run() {
+        StopReason::Breakpoint { pc } => assert_eq!(pc, pcs[3]),
+        r => panic!("{:?}", r),
+    }
+    assert_eq!(spike.pc(), pcs[3]);
+    // Both elements are written, the watchpoint reports the first.
+    match spike.run() {
+        StopReason::Watchpoint {
+            pc,
+            addr,
+            len,
+            access,
+        } => assert_eq!((pc, addr, len, access), (pcs[4], DATA, 8, Access::Write)),
+        r => panic!("{:?}", r),
+    }
+    match spike.run() {
+        StopReason::Condition { pc, condition } => {
+            assert_eq!((pc, condition), (pcs[5], Condition::VlChanged))
+        }
+        r => panic!("{:?}", r),
+    }
+    assert_eq!(spike.get_vl(), 1);
+    match spike.run() {
+        StopReason::Trap { pc, error } => {
+            assert_eq!((pc, error.trap_cause()), (pcs[6], Some(CAUSE_MACHINE_ECALL)))
+        }
+        r => panic!("{:?}", r),
+    }
+    let mut data = [0u8; 16];
+    spike.load_mem(DATA, &mut data).unwrap();
+    assert_eq!(data, [2; 16]);
+
+    // Start over, stopping where v8 is written.
+    assert!(spike.remove_breakpoint(pcs[3]));
+    assert!(spike.remove_watchpoint(DATA..DATA + 8, Access::Write));
+    assert!(spike.remove_condition(Condition::VlChanged));
+    spike.add_condition(Condition::VregWritten(VReg::V8));
+    spike.set_pc(START_MEM);
+    match spike.run() {
+        StopReason::Condition { pc, .. } => assert_eq!(pc, pcs[2]),
+        r => panic!("{:?}", r),
+    }
+    match spike.step() {
+        StopReason::Condition { pc, .. } => assert_eq!(pc, pcs[3]),
+        r => panic!("{:?}", r),
+    }
+    match spike.step() {
+        StopReason::Limit { pc } => assert_eq!(pc, pcs[5]),
+        r => panic!("{:?}", r),
+    }
+    println!("done");
+}
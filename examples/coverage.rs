@@ -0,0 +1,53 @@
+use spike_sys::decode::Category;
+use spike_sys::vtype::Lmul;
+use spike_sys::{Spike, SpikePool};
+
+pub fn main() {
+    let spike = Spike::new(128, 64, 4096).unwrap();
+    spike.set_coverage(true);
+    spike
+        .execute_asm(
+            "
+            vsetivli t0, 4, e32, m1
+            vadd.vv v2, v4, v6
+            vadd.vv v2, v2, v6
+            vsetivli t0, 4, e64, m2
+            vadd.vv v2, v4, v6
+            ",
+        )
+        .unwrap();
+    let coverage = spike.coverage();
+    assert_eq!(coverage.retired, 5);
+    assert_eq!(coverage.mnemonics["vadd.vv"], 3);
+    assert_eq!(coverage.mnemonics["vsetivli"], 2);
+    assert_eq!(coverage.vtypes[&("vadd.vv".to_string(), 32, Lmul::M1)], 2);
+    assert_eq!(coverage.vtypes[&("vadd.vv".to_string(), 64, Lmul::M2)], 1);
+    let never = coverage.never_executed().unwrap();
+    assert!(!never[&Category::Arith].contains(&"vadd.vv".to_string()));
+    assert!(never[&Category::Arith].contains(&"vsub.vv".to_string()));
+
+    // Coverage is cleared by a reset, the retired count is always kept.
+    spike.reset();
+    assert_eq!(spike.coverage(), Default::default());
+    spike.set_coverage(false);
+    spike.execute_asm("vsetivli t0, 4, e32, m1").unwrap();
+    assert_eq!(spike.retired(), 1);
+    assert!(spike.coverage().mnemonics.is_empty());
+
+    // A pool merges the coverage of its processors as they are returned.
+    let pool = SpikePool::new(128, 64, 0);
+    pool.enable_coverage();
+    for sew in ["e8", "e16"].iter() {
+        let spike = pool.get().unwrap();
+        spike
+            .execute_asm(&format!("vsetivli t0, 4, {}, m1\nvsub.vv v2, v4, v6", sew))
+            .unwrap();
+    }
+    let mut coverage = pool.coverage().unwrap();
+    assert_eq!(coverage.retired, 4);
+    assert_eq!(coverage.mnemonics["vsub.vv"], 2);
+    coverage.merge(&spike.coverage());
+    coverage.merge(&Spike::new(128, 64, 0).unwrap().coverage());
+    assert_eq!(coverage.retired, 5);
+    print!("{}", coverage.report().unwrap());
+}
@@ -0,0 +1,34 @@
+use spike_sys::decode::{decode, Category, Operand};
+use spike_sys::insn::{VReg, XReg};
+
+pub fn main() {
+    let insn = decode(0x02aa0157).unwrap();
+    assert_eq!(insn.mnemonic, "vadd.vv");
+    assert_eq!(insn.length, 4);
+    assert_eq!(insn.category, Category::Arith);
+    assert_eq!(
+        insn.operands,
+        vec![
+            Operand::Vd(VReg::V2),
+            Operand::Vs2(VReg::V10),
+            Operand::Vs1(VReg::V20),
+            Operand::Vm(1)
+        ]
+    );
+    assert!(!insn.masked());
+
+    let insn = decode(0x02a2c157).unwrap();
+    assert_eq!(insn.mnemonic, "vadd.vx");
+    assert_eq!(insn.operands[2], Operand::Rs1(XReg::T0));
+
+    let insn = decode(0xc18472d7).unwrap();
+    assert_eq!(insn.mnemonic, "vsetivli");
+    assert_eq!(insn.category, Category::Config);
+
+    // c.li t0, 1
+    let insn = decode(0x4285).unwrap();
+    assert_eq!(insn.length, 2);
+    assert_eq!(insn.category, Category::Scalar);
+
+    assert!(decode(0xffffffff).is_none());
+}
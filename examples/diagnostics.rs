@@ -0,0 +1,63 @@
+use spike_sys::diagnostics::{set_diagnostics, Diagnostics};
+use spike_sys::ffi::*;
+use spike_sys::{ConfigError, Spike};
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+/// Collects everything logged, so the example can check what Spike reported.
+struct Capture(Mutex<Vec<(log::Level, String)>>);
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "spike"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+            self.0.lock().unwrap().push((record.level(), message));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+
+pub fn main() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    let spike = Spike::new(128, 64, 4096).unwrap();
+
+    // Bounds errors are logged as errors under the `spike` target.
+    let mut buf = [0u8; 8];
+    assert!(spike.load_mem(1 << 20, &mut buf).is_err());
+    {
+        let logged = LOGGER.0.lock().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].0, log::Level::Error);
+        println!("logged: {}", logged[0].1);
+    }
+
+    // The error is still returned when diagnostics are silenced.
+    set_diagnostics(Diagnostics::Silent);
+    assert!(spike.load_mem(1 << 20, &mut buf).is_err());
+    assert_eq!(LOGGER.0.lock().unwrap().len(), 1);
+
+    // Configurations Spike would abort on are rejected, with the constraint
+    // that failed.
+    match Spike::new(64, 128, 0) {
+        Err(ConfigError::ElenExceedsVlen { vlen: 64, elen: 128 }) => {}
+        r => panic!("{:?}", r.err()),
+    }
+    assert!(matches!(Spike::new(96, 64, 0), Err(ConfigError::VlenNotPowerOfTwo(96))));
+    assert!(matches!(Spike::new(128, 2048, 0), Err(ConfigError::ElenOutOfRange(2048))));
+    assert!(matches!(Spike::new(128, 64, u64::MAX), Err(ConfigError::MemSizeTooLarge(_))));
+
+    // The shim checks too, for callers of the raw interface.
+    let mut addr = 0;
+    let r = unsafe { rvv_new_processor(64, 128, 0, &mut addr) };
+    assert_eq!(r, RVV_ERROR_INVALID_CONFIG);
+    let message = unsafe { CStr::from_ptr(rvv_last_error()) };
+    assert_eq!(message.to_str().unwrap(), "vlen must be >= elen");
+}
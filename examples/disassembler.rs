@@ -1,6 +1,7 @@
 use std::{ffi::CStr, os::raw::c_char};
 
-use spike_sys::*;
+use spike_sys::disasm::{Disassembler, Immediates};
+use spike_sys::ffi::*;
 
 fn to_string(str: &[u8]) -> String {
     unsafe {
@@ -12,22 +13,39 @@ fn to_string(str: &[u8]) -> String {
 }
 
 pub fn main() {
-    let dis = unsafe { rvv_new_disassembler(64) };
-    assert!(dis != 0);
+    let mut dis = 0;
+    let result = unsafe { rvv_new_disassembler(64, &mut dis) };
+    assert_eq!(result, 0);
     let mut output: Vec<u8> = Vec::new();
     output.resize(128, 0);
 
     let inst: u64 = 0xc18472d7;
     let mut output_len = output.len() as u32;
-    let result = unsafe { rvv_disassemble(dis, inst, (&mut output).as_mut_ptr(), &mut output_len) };
+    let result = unsafe { rvv_disassemble(dis, inst, (&mut output).as_mut_ptr() as *mut c_char, &mut output_len) };
     assert_eq!(result, 0);
     let inst_str = to_string(&output);
     assert_eq!(inst_str, "vsetivli t0, 8, e64, m1, tu, mu");
 
     let inst: u64 = 0x02a2c157;
     let mut output_len = output.len() as u32;
-    let result = unsafe { rvv_disassemble(dis, inst, (&mut output).as_mut_ptr(), &mut output_len) };
+    let result = unsafe { rvv_disassemble(dis, inst, (&mut output).as_mut_ptr() as *mut c_char, &mut output_len) };
     assert_eq!(result, 0);
     let inst_str = to_string(&output);
     assert_eq!(inst_str, "vadd.vx v2, v10, t0");
+
+    let dis = Disassembler::new(64).unwrap();
+    // li t0, 1; vsetivli t0, 8, e64, m1; vadd.vx v2, v10, t0
+    let bytes = [0x85, 0x42, 0xd7, 0x72, 0x84, 0xc1, 0x57, 0xc1, 0xa2, 0x02];
+    let lines = dis.disassemble_buffer(&bytes, 0x1000);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], (0x1000, 0x4285, "c.li t0, 1".to_string()));
+    assert_eq!(lines[1].0, 0x1002);
+    assert_eq!(lines[1].2, "vsetivli t0, 8, e64, m1, tu, mu");
+    assert_eq!(lines[2], (0x1006, 0x02a2c157, "vadd.vx v2, v10, t0".to_string()));
+
+    let mut dis = dis;
+    dis.options.abi_names = false;
+    assert_eq!(dis.disassemble(0x02a2c157).unwrap(), "vadd.vx v2, v10, x5");
+    dis.options.immediates = Immediates::Hex;
+    assert_eq!(dis.disassemble(0x4285).unwrap(), "c.li x5, 0x1");
 }
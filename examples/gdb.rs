@@ -0,0 +1,129 @@
+use std::convert::TryInto;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use spike_sys::asm::{assemble, to_bytes};
+use spike_sys::config::START_MEM;
+use spike_sys::disasm::insn_length;
+use spike_sys::gdb::{serve, DisconnectReason};
+use spike_sys::Spike;
+
+const DATA: u64 = 8192;
+
+// A minimal GDB client speaking the remote protocol.
+struct Client(TcpStream);
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut b = [0u8];
+        self.0.read_exact(&mut b).unwrap();
+        b[0]
+    }
+
+    fn send(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+        write!(self.0, "${}#{:02x}", data, sum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+    }
+
+    fn recv(&mut self) -> String {
+        while self.read_byte() != b'$' {}
+        let mut data = vec![];
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                // run-length encoding: repeat the last byte n - 29 times
+                b'*' => {
+                    let n = self.read_byte() - 29;
+                    let last = *data.last().unwrap();
+                    data.resize(data.len() + n as usize, last);
+                }
+                b => data.push(b),
+            }
+        }
+        self.read_byte();
+        self.read_byte();
+        self.0.write_all(b"+").unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    fn packet(&mut self, data: &str) -> String {
+        self.send(data);
+        self.recv()
+    }
+
+    fn pc(&mut self) -> u64 {
+        let regs = self.packet("g");
+        let pc = &regs[32 * 16..33 * 16];
+        u64::from_le_bytes(hex(pc).try_into().unwrap())
+    }
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+pub fn main() {
+    let insns = assemble(
+        "
+        li a0, 8192
+        vsetivli t1, 2, e64, m1
+        vle64.v v8, (a0)
+        vadd.vv v8, v8, v8
+        vse64.v v8, (a0)
+        ecall
+        ",
+    )
+    .unwrap();
+    let mut pcs = vec![START_MEM];
+    for insn in &insns {
+        pcs.push(pcs.last().unwrap() + insn_length(*insn as u16) as u64);
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let spike = Spike::new(128, 64, 8192).unwrap();
+        spike.store_mem(START_MEM, &to_bytes(&insns)).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        serve(&spike, stream).unwrap()
+    });
+    let mut gdb = Client(TcpStream::connect(addr).unwrap());
+
+    let xml = gdb.packet("qXfer:features:read:target.xml:0,ffff");
+    assert!(xml.starts_with('l'), "{}", xml);
+    assert!(xml.contains("riscv:rv64") && xml.contains(r#"name="v31" bitsize="128""#));
+
+    // Data written by GDB is what the program loads.
+    assert_eq!(gdb.packet(&format!("M{:x},10:{}", DATA, "03".repeat(16))), "OK");
+    assert_eq!(gdb.packet(&format!("Z0,{:x},4", pcs[3])), "OK");
+    assert!(gdb.packet("c").starts_with("T05"));
+    assert_eq!(gdb.pc(), pcs[3]);
+    // x0-x31, pc, f0-f31, 10 CSRs, then v0-v31 of 16 bytes each.
+    let regs = gdb.packet("g");
+    assert_eq!(regs.len(), (75 * 8 + 32 * 16) * 2);
+    let v8 = 75 * 16 + 8 * 32;
+    assert_eq!(&regs[v8..v8 + 32], "03".repeat(16));
+
+    assert!(gdb.packet("s").starts_with("T05"));
+    assert_eq!(gdb.pc(), pcs[4]);
+
+    assert_eq!(gdb.packet(&format!("Z2,{:x},8", DATA)), "OK");
+    let stop = gdb.packet("c");
+    assert!(stop.contains(&format!("watch:{:x}", DATA)), "{}", stop);
+
+    // ecall stops with SIGTRAP.
+    assert!(gdb.packet("c").starts_with("T05"));
+    assert_eq!(gdb.pc(), pcs[5]);
+    assert_eq!(gdb.packet(&format!("m{:x},10", DATA)), "06".repeat(16));
+
+    gdb.send("k");
+    match server.join().unwrap() {
+        DisconnectReason::Kill => {}
+        r => panic!("{:?}", r),
+    }
+    println!("done");
+}
@@ -1,7 +1,9 @@
-use spike_sys::*;
+use spike_sys::ffi::*;
 
 pub fn main() {
-    let proc = unsafe { rvv_new_processor(2048, 1024, 0) };
+    let mut proc = 0;
+    let err = unsafe { rvv_new_processor(2048, 1024, 0, &mut proc) };
+    assert_eq!(err, 0);
     let vlen = unsafe { rvv_get_vlen(proc) };
     let elen = unsafe { rvv_get_elen(proc) };
     println!("vlen = {}, elen = {}", vlen, elen);
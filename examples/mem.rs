@@ -1,7 +1,9 @@
-use spike_sys::*;
+use spike_sys::ffi::*;
 
 pub fn main() {
-    let proc = unsafe { rvv_new_processor(128, 64, 4096) };
+    let mut proc = 0;
+    let err = unsafe { rvv_new_processor(128, 64, 4096, &mut proc) };
+    assert_eq!(err, 0);
     let addr: u64 = 4096;
     let val: u64 = 17;
     // register t0 holds address of "4096"(addr)
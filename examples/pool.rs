@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+use spike_sys::{Spike, SpikePool};
+
+const ROUNDS: u32 = 1000;
+
+fn dirty(spike: &Spike) {
+    spike
+        .execute_asm(
+            "
+            vsetivli t0, 2, e64, m1
+            li t1, 4096
+            vle64.v v2, (t1)
+            vadd.vv v2, v2, v2
+            vse64.v v2, (t1)
+            ",
+        )
+        .unwrap();
+    spike.store_mem(8192, &[0xff; 64]).unwrap();
+}
+
+pub fn main() {
+    let pool = SpikePool::new(128, 64, 1 << 20);
+    {
+        let spike = pool.get().unwrap();
+        dirty(&spike);
+        assert_eq!(spike.get_vl(), 2);
+    }
+    assert_eq!(pool.idle(), 1);
+
+    // A reused processor is in the same state as a new one.
+    let fresh = Spike::new(128, 64, 1 << 20).unwrap();
+    let spike = pool.get().unwrap();
+    assert_eq!(spike.get_vl(), fresh.get_vl());
+    assert_eq!(spike.get_vtype(), fresh.get_vtype());
+    assert_eq!(spike.get_vill(), fresh.get_vill());
+    for i in 0..32 {
+        assert_eq!(spike.get_xreg(i).unwrap(), 0);
+    }
+    let mut vregs = [0xffu8; 16 * 32];
+    spike.get_vreg(0, &mut vregs).unwrap();
+    assert!(vregs.iter().all(|b| *b == 0));
+    let mut mem = [0xffu8; 8192];
+    spike.load_mem(4096, &mut mem).unwrap();
+    assert!(mem.iter().all(|b| *b == 0));
+    drop(spike);
+
+    let stats = pool.stats();
+    assert_eq!((stats.created, stats.reused, stats.resets), (1, 1, 2));
+    // From the vse64.v at 4096 to the end of the store at 8192.
+    assert_eq!(stats.bytes_cleared, 4096 + 64);
+
+    // Commit logging survives resets, so such processors aren't reused.
+    let spike = pool.get().unwrap();
+    spike.enable_commit_log();
+    drop(spike);
+    assert_eq!((pool.idle(), pool.stats().discarded), (0, 1));
+    assert!(!pool.get().unwrap().commit_log_enabled());
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        dirty(&Spike::new(128, 64, 1 << 20).unwrap());
+    }
+    let new = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        dirty(&pool.get().unwrap());
+    }
+    let pooled = start.elapsed();
+    println!("new: {:?}, pooled: {:?}, {:?}", new / ROUNDS, pooled / ROUNDS, pool.stats());
+}
@@ -0,0 +1,38 @@
+use spike_sys::repl::Repl;
+use spike_sys::Spike;
+
+pub fn main() {
+    let mut repl = Repl::new(Spike::new(128, 64, 65536).unwrap());
+    let mut eval = |line: &str| {
+        let out = repl.eval(line).unwrap();
+        println!("> {}\n{}", line, out);
+        out
+    };
+
+    assert_eq!(
+        eval("vsetivli t0, 8, e64, m1"),
+        "t0 = 0x2 (2)\nvl = 2, vtype = e64, m1, tu, mu (0x18), vstart = 0"
+    );
+    assert_eq!(eval("v v10 = 1, 2"), "v10 e64 = [0x1, 0x2]");
+    assert_eq!(eval("v v20 e32 = 10, 20, 30, 40"), "v20 e32 = [0xa, 0x14, 0x1e, 0x28]");
+    assert_eq!(eval("vadd.vv v2, v10, v20"), "v2 e64 = [0x140000000b, 0x2800000020]");
+    assert_eq!(eval("v v2 e32"), "v2 e32 = [0xb, 0x14, 0x20, 0x28]");
+
+    assert_eq!(eval("x t0 = 4096"), "t0 = 0x1000 (4096)");
+    assert_eq!(eval("mem 0x1000 e16 = 1, 2, 3"), "");
+    // vl is 2, so two bytes are loaded.
+    assert_eq!(eval("vle8.v v4, (t0)"), "v4 e64 = [0x1, 0x0]");
+    assert_eq!(eval("vse64.v v2, (t0)"), "");
+    assert_eq!(
+        eval("mem 0x1000 16"),
+        "0x00001000: 0b 00 00 00 14 00 00 00 20 00 00 00 28 00 00 00"
+    );
+    assert_eq!(eval("mem 0x1000 16 e64"), "0x00001000: 0x140000000b 0x2800000020");
+
+    // Traps are reported as errors, nothing is printed for comments.
+    assert!(repl.eval("vsetivli t0, 8, e128, m1").is_ok());
+    assert!(repl.eval("vadd.vv v2, v10, v20").is_err());
+    assert_eq!(repl.eval("# nothing").unwrap(), "");
+    assert!(repl.eval("v v32").is_err());
+    println!("done");
+}
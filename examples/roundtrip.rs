@@ -0,0 +1,21 @@
+use spike_sys::insn::{OPIVI, OPIVV};
+use spike_sys::roundtrip::{check, sweep, RoundTripErrorKind};
+
+const OP_V: u32 = 0b1010111;
+
+pub fn main() {
+    // vadd.vv v2, v10, v20
+    assert_eq!(check(0x02aa0157), Ok(()));
+    // not an instruction at all
+    assert_eq!(check(0xffffffff).unwrap_err().kind, RoundTripErrorKind::Undecodable);
+
+    // every vadd.vv and vadd.vi
+    for funct3 in [OPIVV, OPIVI].iter() {
+        let r = sweep(OP_V, Some(*funct3), Some(0));
+        for e in r.errors.iter() {
+            println!("{}", e);
+        }
+        assert!(r.errors.is_empty());
+        assert_eq!(r.passed, 1 << 16);
+    }
+}
@@ -0,0 +1,68 @@
+use spike_sys::asm::{assemble, to_bytes};
+use spike_sys::config::START_MEM;
+use spike_sys::insn::{VReg, XReg};
+use spike_sys::run::StopReason;
+use spike_sys::trace::Effect;
+use spike_sys::Spike;
+
+pub fn main() {
+    let insns = assemble(
+        "
+        li a0, 8192
+        li a1, 3
+        sd a1, 0(a0)
+        vsetivli t0, 2, e64, m1
+        vle64.v v8, (a0)
+        ecall
+        ",
+    )
+    .unwrap();
+    let spike = Spike::new(128, 64, 8192).unwrap();
+    spike.store_mem(START_MEM, &to_bytes(&insns)).unwrap();
+    spike.enable_commit_log();
+
+    let mut commits = vec![];
+    loop {
+        let (stop, commit) = spike.step_traced();
+        if let Some(commit) = commit {
+            println!("{}", commit);
+            commits.push(commit);
+        }
+        if let StopReason::Trap { .. } = stop {
+            break;
+        }
+    }
+    // The ecall traps, so it isn't committed.
+    assert_eq!(commits.len(), 5);
+    assert_eq!(commits[0].pc, START_MEM);
+    assert_eq!(
+        commits[0].effects,
+        vec![Effect::Xreg {
+            reg: XReg::new(10).unwrap(),
+            value: 8192
+        }]
+    );
+    assert_eq!(
+        commits[2].effects,
+        vec![Effect::Store {
+            addr: 8192,
+            value: 3,
+            size: 8
+        }]
+    );
+    assert_eq!(
+        commits[2].to_string(),
+        format!(
+            "core   0: 3 0x{:016x} (0x{:08x}) mem 0x0000000000002000 0x0000000000000003",
+            commits[2].pc, commits[2].insn
+        )
+    );
+    let mut v8 = vec![0; 16];
+    v8[0] = 3;
+    assert!(commits[4].effects.contains(&Effect::Vreg {
+        reg: VReg::new(8).unwrap(),
+        value: v8
+    }));
+    assert!(commits[4].effects.contains(&Effect::Load { addr: 8192, size: 8 }));
+    println!("done");
+}
@@ -0,0 +1,96 @@
+use spike_sys::asm::{assemble, to_bytes};
+use spike_sys::config::START_MEM;
+use spike_sys::disasm::Disassembler;
+use spike_sys::insn::VReg;
+use spike_sys::run::StopReason;
+use spike_sys::trace::{execute_traced, parse, Commit, Effect};
+use spike_sys::tracediff::{align, diff, Mismatch};
+use spike_sys::Spike;
+
+fn run(program: &str) -> Vec<Commit> {
+    let spike = Spike::new(128, 64, 8192).unwrap();
+    spike
+        .store_mem(START_MEM, &to_bytes(&assemble(program).unwrap()))
+        .unwrap();
+    spike.enable_commit_log();
+    let mut commits = vec![];
+    loop {
+        let (stop, commit) = spike.step_traced();
+        commits.extend(commit);
+        if let StopReason::Trap { .. } = stop {
+            return commits;
+        }
+    }
+}
+
+pub fn main() {
+    let program = "
+        li a0, 8192
+        li a1, 3
+        sd a1, 0(a0)
+        vsetivli t0, 2, e64, m1
+        vle64.v v8, (a0)
+        vadd.vv v8, v8, v8
+        vse64.v v8, (a0)
+        ecall
+    ";
+    let left = run(program);
+    // Logs survive being written and parsed, except for the sizes of loads,
+    // along with Spike's vector configuration and lines that aren't commits.
+    let mut log: String = left.iter().map(|c| format!("{}\n", c)).collect();
+    let parsed: String = parse(&log).unwrap().iter().map(|c| format!("{}\n", c)).collect();
+    assert_eq!(parsed, log);
+    log = log.replace(" v8 ", " e64 m1 l2 v8 ");
+    log.insert_str(0, "bbl loader\ncore   0: 0x0000000000001000 (0x00002537) lui a0, 2\n");
+    assert_eq!(diff(&left, &parse(&log).unwrap(), (0, 0)), None);
+    assert_eq!(parse("core   0: 3 0x1000 (0x00002537) x10").unwrap_err().line, 1);
+
+    assert_eq!(align(&left, &left), Some((0, 0)));
+    assert_eq!(diff(&left, &left, (0, 0)), None);
+    // The right log starts one instruction later, at the second one.
+    assert_eq!(align(&left, &left[1..]), Some((1, 0)));
+
+    let right = run(&program.replace("vadd.vv", "vsub.vv"));
+    let divergence = diff(&left, &right, (0, 0)).unwrap();
+    assert_eq!((divergence.left, divergence.right), (5, 5));
+    assert_eq!(divergence.mismatch, Mismatch::Insn);
+
+    // Tamper with the value vle64.v loads.
+    let mut right = left.clone();
+    for effect in &mut right[4].effects {
+        if let Effect::Vreg { value, .. } = effect {
+            value[0] = 4;
+        }
+    }
+    let divergence = diff(&left, &right, (0, 0)).unwrap();
+    match &divergence.mismatch {
+        Mismatch::Effects { left, right } => {
+            assert_eq!(left.len(), 1);
+            assert!(matches!(right[..], [Effect::Vreg { reg: VReg::V8, .. }]));
+        }
+        m => panic!("{:?}", m),
+    }
+    let report = divergence.report(&left, &right, 2, &Disassembler::new(64).unwrap());
+    println!("{}", report);
+    assert!(report.starts_with("instruction 4: left has v8 "));
+    assert!(report.contains("vle64.v v8, (a0)"));
+    assert_eq!(report.lines().filter(|l| l.starts_with('<')).count(), 2);
+
+    // Models are traced by executing instructions one at a time.
+    let mut spike = Spike::new(128, 64, 8192).unwrap();
+    let insns = assemble("li a1, 3\nvsetivli t0, 2, e64, m1\nvmv.v.x v8, a1").unwrap();
+    let commits: Vec<Commit> = insns
+        .iter()
+        .enumerate()
+        .map(|(i, insn)| execute_traced(&mut spike, 4 * i as u64, *insn).unwrap())
+        .collect();
+    assert_eq!(commits[1].effects.len(), 3);
+    assert_eq!(
+        commits[2].effects,
+        vec![Effect::Vreg {
+            reg: VReg::V8,
+            value: [3u64.to_le_bytes(), 3u64.to_le_bytes()].concat()
+        }]
+    );
+    println!("done");
+}
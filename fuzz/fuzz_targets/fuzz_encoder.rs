@@ -1,12 +1,11 @@
 #![no_main]
-use lazy_static::lazy_static;
 use libfuzzer_sys::fuzz_target;
-use rvv_encode::encode;
-use std::collections::HashSet;
-use std::str;
+use spike_sys::roundtrip::{check, RoundTripErrorKind};
 
+// The instructions rvv-encode handles, by Spike's mnemonic. Others, e.g.
+// segment and fault-only-first loads, aren't checked.
 #[rustfmt::skip]
-const COMPLETED_RVV_INST_VEC: [&str; 269] = [
+const SUPPORTED: [&str; 269] = [
     //"vsetivli",
     //"vsetvli",
     "vsetvl",
@@ -295,74 +294,15 @@ const COMPLETED_RVV_INST_VEC: [&str; 269] = [
     "vrgather.vi",
 ];
 
-lazy_static! {
-    static ref COMPLETED_RVV_INST: HashSet<&'static str> = HashSet::from(COMPLETED_RVV_INST_VEC);
-}
-
-fn check_asm(inst: u32, xlen: u32) {
-    let dis = unsafe { spike_sys::rvv_new_disassembler(xlen) };
-
-    let mut output = [0u8; 64];
-    let mut output_len = output.len() as u32;
-    let ret = unsafe { spike_sys::rvv_disassemble(dis, inst as u64, output.as_mut_ptr(), &mut output_len) };
-    unsafe {
-        spike_sys::rvv_delete_disassembler(dis);
-    }
-
-    if ret != 0 {
-        return;
-    }
-    let asm_str = str::from_utf8(&output[0..output_len as usize]).ok().unwrap();
-    if asm_str.find("unknown").is_some() {
-        return;
-    }
-
-    let inst_name = {
-        let i = asm_str.find(" ");
-        if i.is_none() {
-            Option::None
-        } else {
-            Option::Some(&asm_str[0..i.unwrap()])
-        }
+fuzz_target!(|data: [u8; 4]| {
+    let inst = u32::from_le_bytes(data);
+    let e = match check(inst) {
+        Ok(()) => return,
+        Err(e) => e,
     };
-    if inst_name.is_none() {
+    let mnemonic = e.text.split_whitespace().next().unwrap_or("");
+    if !SUPPORTED.contains(&mnemonic) || e.kind == RoundTripErrorKind::Undecodable {
         return;
     }
-
-    if COMPLETED_RVV_INST.get(inst_name.unwrap()).is_none() {
-        return;
-    }
-
-    let inst2 = encode(asm_str, false);
-    assert!(inst2.is_ok(), "Encode error: inst: 0x{:x?}, asm: {}", inst, asm_str);
-
-    let inst2 = inst2.ok();
-    assert!(
-        inst2.is_some(),
-        "Encode error: inst: 0x{:x?}, asm: {}, inst2: none",
-        inst,
-        asm_str
-    );
-
-    let inst2 = inst2.unwrap();
-    assert!(
-        inst2.is_some(),
-        "Encode error: inst: 0x{:x?}, asm: {}, inst2: none",
-        inst,
-        asm_str
-    );
-
-    let inst2 = inst2.unwrap();
-    assert!(
-        inst == inst2,
-        "Error: inst: 0x{:x?}, rvv_inst: 0x{:x?}, asm: {}",
-        inst,
-        inst2,
-        asm_str
-    );
-}
-
-fuzz_target!(|data: [u8; 4]| {
-    let inst = u32::from_le_bytes(data);
-    check_asm(inst, 0);
+    panic!("{}", e);
 });
@@ -0,0 +1,35 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use spike_sys::generator::{ByteRng, RngCore};
+use spike_sys::vtype::VTypeGenerator;
+use spike_sys::*;
+
+// Only Spike is checked here, against the vl/vill predicted by the generator, so
+// VLENs that ckb-vm doesn't support are covered as well.
+const CONFIGS: [(u32, u32); 6] = [(128, 64), (256, 64), (512, 64), (1024, 128), (2048, 256), (2048, 1024)];
+
+fuzz_target!(|data: &[u8]| {
+    let mut rng = ByteRng::new(data);
+    while rng.remaining() > 0 {
+        let (vlen, elen) = CONFIGS[rng.next_u32() as usize % CONFIGS.len()];
+        let mut spike = Spike::new(vlen, elen, 0).unwrap();
+        let mut generator = VTypeGenerator::new(vlen, elen);
+        generator.allow_illegal = rng.next_u32() & 1 != 0;
+        generator.agnostic = true;
+        let vset = generator.generate(&mut rng);
+        if std::env::var("LOG").is_ok() {
+            println!("vlen={} elen={} vset={:?} insn=0x{:x}", vlen, elen, vset, vset.encode());
+        }
+        vset.apply(&mut spike).unwrap();
+        match vset.decoded(elen) {
+            Some(vtype) => {
+                assert_eq!(spike.get_vill(), 0);
+                assert_eq!(spike.get_sew(), vtype.sew as u64);
+                assert_eq!(spike.get_lmul(), vtype.lmul.as_f32());
+            }
+            None => assert_eq!(spike.get_vill(), 1),
+        }
+        assert_eq!(spike.get_vl(), vset.expected_vl(vlen, elen));
+        assert_eq!(spike.get_xreg(generator.rd as u64).unwrap(), spike.get_vl());
+    }
+});
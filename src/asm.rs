@@ -0,0 +1,848 @@
+//! A small assembler for the instructions fed to Spike.
+//!
+//! Vector instructions are encoded by `rvv-encode`, except for
+//! `vsetvli`/`vsetivli`/`vsetvl` which go through [`VSet`]. Scalar support
+//! covers RV64IM, CSR access and the common pseudo-instructions (`li`, `mv`,
+//! `not`, `neg`, `j`, `ret`, ...). Branch and jump targets are plain byte
+//! offsets, labels aren't supported.
+//!
+//! Like the GNU assembler for RV64GC, instructions with a compressed form are
+//! emitted compressed, so `li t0, 4096` becomes `c.lui t0, 1`. Compressed
+//! instructions only occupy the low 16 bits of their `u32`, see [`insn_len`].
+use crate::insn::{csrs, XREG_NAMES};
+use crate::vtype::{VSet, VSetForm, VType};
+use crate::{Error, Spike};
+
+const OP_IMM: u32 = 0b0010011;
+const OP_IMM_32: u32 = 0b0011011;
+const OP: u32 = 0b0110011;
+const OP_32: u32 = 0b0111011;
+const LUI: u32 = 0b0110111;
+const AUIPC: u32 = 0b0010111;
+const JAL: u32 = 0b1101111;
+const JALR: u32 = 0b1100111;
+const BRANCH: u32 = 0b1100011;
+const LOAD: u32 = 0b0000011;
+const STORE: u32 = 0b0100011;
+const SYSTEM: u32 = 0b1110011;
+
+const EBREAK: u32 = 0x00100073;
+
+const BRANCHES: &[(&str, u32)] = &[
+    ("beq", 0),
+    ("bne", 1),
+    ("blt", 4),
+    ("bge", 5),
+    ("bltu", 6),
+    ("bgeu", 7),
+];
+
+const LOADS: &[(&str, u32)] = &[
+    ("lb", 0),
+    ("lh", 1),
+    ("lw", 2),
+    ("ld", 3),
+    ("lbu", 4),
+    ("lhu", 5),
+    ("lwu", 6),
+];
+
+const STORES: &[(&str, u32)] = &[("sb", 0), ("sh", 1), ("sw", 2), ("sd", 3)];
+
+/// (mnemonic, opcode, funct3)
+const IMMEDIATES: &[(&str, u32, u32)] = &[
+    ("addi", OP_IMM, 0),
+    ("slti", OP_IMM, 2),
+    ("sltiu", OP_IMM, 3),
+    ("xori", OP_IMM, 4),
+    ("ori", OP_IMM, 6),
+    ("andi", OP_IMM, 7),
+    ("addiw", OP_IMM_32, 0),
+];
+
+/// (mnemonic, opcode, funct3, high bits, shamt width)
+const SHIFTS: &[(&str, u32, u32, u32, u32)] = &[
+    ("slli", OP_IMM, 1, 0, 6),
+    ("srli", OP_IMM, 5, 0, 6),
+    ("srai", OP_IMM, 5, 0x4000_0000, 6),
+    ("slliw", OP_IMM_32, 1, 0, 5),
+    ("srliw", OP_IMM_32, 5, 0, 5),
+    ("sraiw", OP_IMM_32, 5, 0x4000_0000, 5),
+];
+
+/// (mnemonic, opcode, funct3, funct7)
+#[rustfmt::skip]
+const REGISTERS: &[(&str, u32, u32, u32)] = &[
+    ("add", OP, 0, 0), ("sub", OP, 0, 0x20), ("sll", OP, 1, 0), ("slt", OP, 2, 0),
+    ("sltu", OP, 3, 0), ("xor", OP, 4, 0), ("srl", OP, 5, 0), ("sra", OP, 5, 0x20),
+    ("or", OP, 6, 0), ("and", OP, 7, 0),
+    ("mul", OP, 0, 1), ("mulh", OP, 1, 1), ("mulhsu", OP, 2, 1), ("mulhu", OP, 3, 1),
+    ("div", OP, 4, 1), ("divu", OP, 5, 1), ("rem", OP, 6, 1), ("remu", OP, 7, 1),
+    ("addw", OP_32, 0, 0), ("subw", OP_32, 0, 0x20), ("sllw", OP_32, 1, 0),
+    ("srlw", OP_32, 5, 0), ("sraw", OP_32, 5, 0x20),
+    ("mulw", OP_32, 0, 1), ("divw", OP_32, 4, 1), ("divuw", OP_32, 5, 1),
+    ("remw", OP_32, 6, 1), ("remuw", OP_32, 7, 1),
+];
+
+/// (mnemonic, funct3)
+const CSR_OPS: &[(&str, u32)] = &[
+    ("csrrw", 1),
+    ("csrrs", 2),
+    ("csrrc", 3),
+    ("csrrwi", 5),
+    ("csrrsi", 6),
+    ("csrrci", 7),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    OperandCount {
+        expected: usize,
+        found: usize,
+    },
+    /// A register, immediate, memory operand or vtype that doesn't parse.
+    BadOperand(String),
+    ImmediateRange {
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    /// A `c.` mnemonic whose operands have no compressed encoding.
+    NotCompressible,
+    /// Rejected by `rvv-encode`.
+    Rvv(String),
+}
+
+impl std::fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic `{}`", m),
+            AsmErrorKind::OperandCount { expected, found } => {
+                write!(f, "expected {} operands, found {}", expected, found)
+            }
+            AsmErrorKind::BadOperand(s) => write!(f, "invalid operand `{}`", s),
+            AsmErrorKind::ImmediateRange { value, min, max } => {
+                write!(f, "immediate {} out of range [{}, {}]", value, min, max)
+            }
+            AsmErrorKind::NotCompressible => {
+                f.write_str("no compressed encoding for these operands")
+            }
+            AsmErrorKind::Rvv(e) => write!(f, "rvv-encode: {}", e),
+        }
+    }
+}
+
+/// An error together with the statement it was found in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line number in the source.
+    pub line: usize,
+    pub text: String,
+    pub kind: AsmErrorKind,
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: `{}`: {}", self.line, self.text, self.kind)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Error returned by [`Spike::execute_asm`].
+#[derive(Debug)]
+pub enum ExecuteAsmError {
+    Asm(AsmError),
+    Execute { insn: u32, error: Error },
+}
+
+impl std::fmt::Display for ExecuteAsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteAsmError::Asm(e) => e.fmt(f),
+            ExecuteAsmError::Execute { insn, error } => {
+                write!(f, "executing 0x{:08x}: {}", insn, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecuteAsmError {}
+
+pub struct Assembler {
+    /// Emit compressed encodings where possible. On by default.
+    pub compressed: bool,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self { compressed: true }
+    }
+
+    /// Assemble `src`. Statements are separated by newlines or `;`, and `#`
+    /// or `//` start a comment.
+    pub fn assemble(&self, src: &str) -> Result<Vec<u32>, AsmError> {
+        let mut r = vec![];
+        for (i, line) in src.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(n) => &line[..n],
+                None => line,
+            };
+            let line = match line.find("//") {
+                Some(n) => &line[..n],
+                None => line,
+            };
+            for stmt in line.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                let insns = self.assemble_statement(stmt).map_err(|kind| AsmError {
+                    line: i + 1,
+                    text: stmt.to_string(),
+                    kind,
+                })?;
+                r.extend(insns);
+            }
+        }
+        Ok(r)
+    }
+
+    /// Assemble a single statement. Pseudo-instructions like `li` may expand
+    /// to several instructions.
+    pub fn assemble_statement(&self, stmt: &str) -> Result<Vec<u32>, AsmErrorKind> {
+        let stmt = stmt.trim();
+        let (mnemonic, rest) = match stmt.find(char::is_whitespace) {
+            Some(n) => (&stmt[..n], stmt[n..].trim()),
+            None => (stmt, ""),
+        };
+        let ops: Vec<&str> = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        if let Some(name) = mnemonic.strip_prefix("c.") {
+            let insn = uncompressed(name, &ops)?;
+            return match compress(insn) {
+                Some(insn) => Ok(vec![insn]),
+                None => Err(AsmErrorKind::NotCompressible),
+            };
+        }
+        let insns = match mnemonic {
+            "vsetvli" | "vsetivli" | "vsetvl" => vec![vset(mnemonic, &ops)?.encode()],
+            m if m.starts_with('v') => vec![rvv(mnemonic, stmt)?],
+            "li" => {
+                expect(&ops, 2)?;
+                li(xreg(ops[0])?, imm(ops[1])?)
+            }
+            _ => vec![scalar(mnemonic, &ops)?],
+        };
+        if self.compressed {
+            Ok(insns
+                .into_iter()
+                .map(|insn| compress(insn).unwrap_or(insn))
+                .collect())
+        } else {
+            Ok(insns)
+        }
+    }
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Assemble `src` with the default [`Assembler`].
+pub fn assemble(src: &str) -> Result<Vec<u32>, AsmError> {
+    Assembler::new().assemble(src)
+}
+
+/// Assemble one or more statements with the default assembler, e.g.
+/// `assemble!("li t0, 4096", "vsetvli t1, t0, e64, m1")`.
+#[macro_export]
+macro_rules! assemble {
+    ($($stmt:expr),+ $(,)?) => {
+        $crate::asm::assemble(&[$($stmt),+].join("\n"))
+    };
+}
+
+/// The length in bytes of an encoded instruction: 2 if compressed, otherwise 4.
+pub fn insn_len(insn: u32) -> usize {
+    if insn & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// Lay instructions out in memory, little endian.
+pub fn to_bytes(insns: &[u32]) -> Vec<u8> {
+    let mut r = vec![];
+    for insn in insns {
+        r.extend_from_slice(&insn.to_le_bytes()[..insn_len(*insn)]);
+    }
+    r
+}
+
+impl Spike {
+    /// Assemble `src` and execute the instructions in order, stopping at the
+    /// first one that traps.
+    pub fn execute_asm(&self, src: &str) -> Result<(), ExecuteAsmError> {
+        let insns = assemble(src).map_err(ExecuteAsmError::Asm)?;
+        let r = self.execute_batch(&insns);
+        match r.trap {
+            Some(error) => Err(ExecuteAsmError::Execute {
+                insn: insns[r.retired],
+                error,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn expect(ops: &[&str], n: usize) -> Result<(), AsmErrorKind> {
+    if ops.len() != n {
+        return Err(AsmErrorKind::OperandCount {
+            expected: n,
+            found: ops.len(),
+        });
+    }
+    Ok(())
+}
+
+fn bad(s: &str) -> AsmErrorKind {
+    AsmErrorKind::BadOperand(s.to_string())
+}
+
+pub(crate) fn xreg(s: &str) -> Result<u32, AsmErrorKind> {
+    if s == "fp" {
+        return Ok(8);
+    }
+    if let Some(n) = XREG_NAMES.iter().position(|r| *r == s) {
+        return Ok(n as u32);
+    }
+    match s.strip_prefix('x').map(str::parse::<u32>) {
+        Some(Ok(n)) if n < 32 => Ok(n),
+        _ => Err(bad(s)),
+    }
+}
+
+/// Decimal, `0x` hex or `0b` binary, optionally negative. Values up to
+/// `u64::MAX` are accepted and wrap to `i64`.
+pub(crate) fn imm(s: &str) -> Result<i64, AsmErrorKind> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(d) => (true, d),
+        None => (false, s),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        digits.parse::<u64>()
+    }
+    .map_err(|_| bad(s))?;
+    if negative {
+        if value > 1 << 63 {
+            return Err(bad(s));
+        }
+        Ok((value as i64).wrapping_neg())
+    } else {
+        Ok(value as i64)
+    }
+}
+
+fn ranged(value: i64, min: i64, max: i64) -> Result<i64, AsmErrorKind> {
+    if value < min || value > max {
+        return Err(AsmErrorKind::ImmediateRange { value, min, max });
+    }
+    Ok(value)
+}
+
+/// A signed immediate of `bits` bits.
+fn simm(s: &str, bits: u32) -> Result<i64, AsmErrorKind> {
+    ranged(imm(s)?, -(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+}
+
+/// A branch or jump offset: signed, `bits` wide and even.
+fn offset(s: &str, bits: u32) -> Result<i64, AsmErrorKind> {
+    let value = simm(s, bits)?;
+    if value % 2 != 0 {
+        return Err(bad(s));
+    }
+    Ok(value)
+}
+
+/// `offset(reg)`, the offset may be omitted.
+fn mem(s: &str) -> Result<(i64, u32), AsmErrorKind> {
+    let open = s.find('(').ok_or_else(|| bad(s))?;
+    let reg = s[open + 1..].strip_suffix(')').ok_or_else(|| bad(s))?;
+    let offset = s[..open].trim();
+    let offset = if offset.is_empty() {
+        0
+    } else {
+        simm(offset, 12)?
+    };
+    Ok((offset, xreg(reg.trim())?))
+}
+
+fn csr(s: &str) -> Result<u32, AsmErrorKind> {
+    if let Some((_, n)) = csrs().find(|(name, _)| *name == s) {
+        return Ok(*n);
+    }
+    Ok(ranged(imm(s)?, 0, 0xfff)? as u32)
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], m: &str) -> Option<T> {
+    table.iter().find(|(name, _)| *name == m).map(|(_, v)| *v)
+}
+
+fn r(opcode: u32, funct3: u32, funct7: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn i(opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i64) -> u32 {
+    (imm as u32 & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn s(funct3: u32, rs1: u32, rs2: u32, imm: i64) -> u32 {
+    let imm = imm as u32;
+    (imm >> 5 & 0x7f) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | STORE
+}
+
+fn b(funct3: u32, rs1: u32, rs2: u32, imm: i64) -> u32 {
+    let imm = imm as u32;
+    (imm >> 12 & 1) << 31
+        | (imm >> 5 & 0x3f) << 25
+        | rs2 << 20
+        | rs1 << 15
+        | funct3 << 12
+        | (imm >> 1 & 0xf) << 8
+        | (imm >> 11 & 1) << 7
+        | BRANCH
+}
+
+fn u(opcode: u32, rd: u32, imm: i64) -> u32 {
+    (imm as u32 & 0xfffff) << 12 | rd << 7 | opcode
+}
+
+fn j(rd: u32, imm: i64) -> u32 {
+    let imm = imm as u32;
+    (imm >> 20 & 1) << 31
+        | (imm >> 1 & 0x3ff) << 21
+        | (imm >> 11 & 1) << 20
+        | (imm >> 12 & 0xff) << 12
+        | rd << 7
+        | JAL
+}
+
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    ((value << (64 - bits)) as i64) >> (64 - bits)
+}
+
+/// Materialize a 64-bit constant the way LLVM and GNU as do: `lui`/`addiw`
+/// for 32-bit values, otherwise the upper bits recursively followed by
+/// `slli` and `addi`.
+fn li(rd: u32, value: i64) -> Vec<u32> {
+    let mut r = vec![];
+    materialize(rd, value, &mut r);
+    r
+}
+
+fn materialize(rd: u32, value: i64, out: &mut Vec<u32>) {
+    let lo12 = sign_extend(value as u64 & 0xfff, 12);
+    if value == value as i32 as i64 {
+        let hi20 = (value + 0x800) >> 12 & 0xfffff;
+        if hi20 == 0 {
+            out.push(i(OP_IMM, 0, rd, 0, lo12));
+        } else {
+            out.push(u(LUI, rd, hi20));
+            if lo12 != 0 {
+                out.push(i(OP_IMM_32, 0, rd, rd, lo12));
+            }
+        }
+        return;
+    }
+    let hi52 = value.wrapping_sub(lo12) >> 12;
+    let shift = 12 + hi52.trailing_zeros();
+    materialize(rd, hi52 >> (shift - 12), out);
+    out.push(i(OP_IMM, 1, rd, rd, shift as i64));
+    if lo12 != 0 {
+        out.push(i(OP_IMM, 0, rd, rd, lo12));
+    }
+}
+
+fn vset(m: &str, ops: &[&str]) -> Result<VSet, AsmErrorKind> {
+    let form = match m {
+        "vsetvli" => VSetForm::Vsetvli,
+        "vsetivli" => VSetForm::Vsetivli,
+        _ => VSetForm::Vsetvl,
+    };
+    let mut vset = VSet {
+        form,
+        vtype: 0,
+        avl: 0,
+        rd: 0,
+        rs1: 0,
+        rs2: 0,
+    };
+    if form == VSetForm::Vsetvl {
+        expect(ops, 3)?;
+        vset.rd = xreg(ops[0])?;
+        vset.rs1 = xreg(ops[1])?;
+        vset.rs2 = xreg(ops[2])?;
+        return Ok(vset);
+    }
+    if ops.len() < 3 {
+        return Err(AsmErrorKind::OperandCount {
+            expected: 3,
+            found: ops.len(),
+        });
+    }
+    vset.rd = xreg(ops[0])?;
+    if form == VSetForm::Vsetivli {
+        vset.avl = ranged(imm(ops[1])?, 0, 31)? as u64;
+    } else {
+        vset.rs1 = xreg(ops[1])?;
+    }
+    let vtype = ops[2..].join(", ");
+    vset.vtype = VType::parse(&vtype).ok_or_else(|| bad(&vtype))?.bits();
+    Ok(vset)
+}
+
+fn rvv(m: &str, stmt: &str) -> Result<u32, AsmErrorKind> {
+    match rvv_encode::encode(stmt, false) {
+        Ok(Some(insn)) => Ok(insn),
+        Ok(None) => Err(AsmErrorKind::UnknownMnemonic(m.to_string())),
+        Err(e) => Err(AsmErrorKind::Rvv(e.to_string())),
+    }
+}
+
+fn scalar(m: &str, ops: &[&str]) -> Result<u32, AsmErrorKind> {
+    if let Some(funct3) = lookup(BRANCHES, m) {
+        expect(ops, 3)?;
+        return Ok(b(funct3, xreg(ops[0])?, xreg(ops[1])?, offset(ops[2], 13)?));
+    }
+    if let Some(funct3) = lookup(LOADS, m) {
+        expect(ops, 2)?;
+        let (imm, base) = mem(ops[1])?;
+        return Ok(i(LOAD, funct3, xreg(ops[0])?, base, imm));
+    }
+    if let Some(funct3) = lookup(STORES, m) {
+        expect(ops, 2)?;
+        let (imm, base) = mem(ops[1])?;
+        return Ok(s(funct3, base, xreg(ops[0])?, imm));
+    }
+    if let Some((_, opcode, funct3)) = IMMEDIATES.iter().find(|o| o.0 == m) {
+        expect(ops, 3)?;
+        return Ok(i(
+            *opcode,
+            *funct3,
+            xreg(ops[0])?,
+            xreg(ops[1])?,
+            simm(ops[2], 12)?,
+        ));
+    }
+    if let Some((_, opcode, funct3, high, width)) = SHIFTS.iter().find(|o| o.0 == m) {
+        expect(ops, 3)?;
+        let shamt = ranged(imm(ops[2])?, 0, (1 << width) - 1)?;
+        return Ok(high | i(*opcode, *funct3, xreg(ops[0])?, xreg(ops[1])?, shamt));
+    }
+    if let Some((_, opcode, funct3, funct7)) = REGISTERS.iter().find(|o| o.0 == m) {
+        expect(ops, 3)?;
+        return Ok(r(
+            *opcode,
+            *funct3,
+            *funct7,
+            xreg(ops[0])?,
+            xreg(ops[1])?,
+            xreg(ops[2])?,
+        ));
+    }
+    if let Some(funct3) = lookup(CSR_OPS, m) {
+        expect(ops, 3)?;
+        let source = if funct3 & 0b100 != 0 {
+            ranged(imm(ops[2])?, 0, 31)? as u32
+        } else {
+            xreg(ops[2])?
+        };
+        return Ok(csr(ops[1])? << 20 | source << 15 | funct3 << 12 | xreg(ops[0])? << 7 | SYSTEM);
+    }
+    let insn = match m {
+        "lui" | "auipc" => {
+            expect(ops, 2)?;
+            let opcode = if m == "lui" { LUI } else { AUIPC };
+            u(
+                opcode,
+                xreg(ops[0])?,
+                ranged(imm(ops[1])?, -(1 << 19), (1 << 20) - 1)?,
+            )
+        }
+        "jal" => match ops.len() {
+            1 => j(1, offset(ops[0], 21)?),
+            _ => {
+                expect(ops, 2)?;
+                j(xreg(ops[0])?, offset(ops[1], 21)?)
+            }
+        },
+        "jalr" => match ops.len() {
+            1 => i(JALR, 0, 1, xreg(ops[0])?, 0),
+            2 => {
+                let (imm, base) = mem(ops[1])?;
+                i(JALR, 0, xreg(ops[0])?, base, imm)
+            }
+            _ => {
+                expect(ops, 3)?;
+                i(JALR, 0, xreg(ops[0])?, xreg(ops[1])?, simm(ops[2], 12)?)
+            }
+        },
+        "j" => {
+            expect(ops, 1)?;
+            j(0, offset(ops[0], 21)?)
+        }
+        "jr" => {
+            expect(ops, 1)?;
+            i(JALR, 0, 0, xreg(ops[0])?, 0)
+        }
+        "ret" => {
+            expect(ops, 0)?;
+            i(JALR, 0, 0, 1, 0)
+        }
+        "nop" => {
+            expect(ops, 0)?;
+            i(OP_IMM, 0, 0, 0, 0)
+        }
+        "mv" | "not" | "sext.w" | "seqz" => {
+            expect(ops, 2)?;
+            let (opcode, funct3, imm) = match m {
+                "mv" => (OP_IMM, 0, 0),
+                "not" => (OP_IMM, 4, -1),
+                "sext.w" => (OP_IMM_32, 0, 0),
+                _ => (OP_IMM, 3, 1),
+            };
+            i(opcode, funct3, xreg(ops[0])?, xreg(ops[1])?, imm)
+        }
+        "neg" | "negw" | "snez" => {
+            expect(ops, 2)?;
+            let (opcode, funct3, funct7) = match m {
+                "neg" => (OP, 0, 0x20),
+                "negw" => (OP_32, 0, 0x20),
+                _ => (OP, 3, 0),
+            };
+            r(opcode, funct3, funct7, xreg(ops[0])?, 0, xreg(ops[1])?)
+        }
+        "csrr" => {
+            expect(ops, 2)?;
+            csr(ops[1])? << 20 | 2 << 12 | xreg(ops[0])? << 7 | SYSTEM
+        }
+        "csrw" => {
+            expect(ops, 2)?;
+            csr(ops[0])? << 20 | xreg(ops[1])? << 15 | 1 << 12 | SYSTEM
+        }
+        "fence" => {
+            expect(ops, 0)?;
+            0x0ff0000f
+        }
+        "ecall" => {
+            expect(ops, 0)?;
+            SYSTEM
+        }
+        "ebreak" => {
+            expect(ops, 0)?;
+            EBREAK
+        }
+        _ => return Err(AsmErrorKind::UnknownMnemonic(m.to_string())),
+    };
+    Ok(insn)
+}
+
+/// The 32-bit equivalent of a `c.` instruction, to be passed to [`compress`].
+fn uncompressed(name: &str, ops: &[&str]) -> Result<u32, AsmErrorKind> {
+    let insn = match name {
+        "nop" | "ebreak" | "jr" | "jalr" => return scalar(name, ops),
+        "li" | "lui" | "addi" | "addiw" | "slli" => {
+            expect(ops, 2)?;
+            let rd = xreg(ops[0])?;
+            match name {
+                "li" => i(OP_IMM, 0, rd, 0, simm(ops[1], 6)?),
+                "lui" => u(LUI, rd, simm(ops[1], 6)?),
+                "addi" => i(OP_IMM, 0, rd, rd, simm(ops[1], 6)?),
+                "addiw" => i(OP_IMM_32, 0, rd, rd, simm(ops[1], 6)?),
+                _ => i(OP_IMM, 1, rd, rd, ranged(imm(ops[1])?, 0, 63)?),
+            }
+        }
+        "mv" | "add" => {
+            expect(ops, 2)?;
+            let rd = xreg(ops[0])?;
+            let rs1 = if name == "mv" { 0 } else { rd };
+            r(OP, 0, 0, rd, rs1, xreg(ops[1])?)
+        }
+        _ => return Err(AsmErrorKind::UnknownMnemonic(format!("c.{}", name))),
+    };
+    Ok(insn)
+}
+
+/// The compressed form of a 32-bit instruction, if it has one. The
+/// stack-pointer relative and `rd'`/`rs1'` forms aren't covered.
+pub fn compress(insn: u32) -> Option<u32> {
+    let opcode = insn & 0x7f;
+    let rd = insn >> 7 & 0b11111;
+    let funct3 = insn >> 12 & 0b111;
+    let rs1 = insn >> 15 & 0b11111;
+    let rs2 = insn >> 20 & 0b11111;
+    let imm = sign_extend(insn as u64 >> 20, 12);
+    let imm6 = |funct3: u32, rd: u32, imm: i64| {
+        if !(-32..32).contains(&imm) {
+            return None;
+        }
+        let imm = imm as u32 & 0x3f;
+        Some(funct3 << 13 | (imm >> 5) << 12 | rd << 7 | (imm & 0x1f) << 2 | 0b01)
+    };
+    match (opcode, funct3) {
+        (OP_IMM, 0) if rd == 0 && rs1 == 0 && imm == 0 => Some(0x0001),
+        (OP_IMM, 0) if rd != 0 && rs1 == 0 => imm6(0b010, rd, imm),
+        (OP_IMM, 0) if rd != 0 && rs1 != 0 && imm == 0 => Some(0x8002 | rd << 7 | rs1 << 2),
+        (OP_IMM, 0) if rd != 0 && rd == rs1 && imm != 0 => imm6(0b000, rd, imm),
+        (OP_IMM_32, 0) if rd != 0 && rd == rs1 => imm6(0b001, rd, imm),
+        (OP_IMM, 1) if rd != 0 && rd == rs1 && insn >> 26 == 0 => {
+            let shamt = insn >> 20 & 0x3f;
+            if shamt == 0 {
+                return None;
+            }
+            Some((shamt >> 5) << 12 | rd << 7 | (shamt & 0x1f) << 2 | 0b10)
+        }
+        (LUI, _) if rd != 0 && rd != 2 => {
+            let imm = sign_extend(insn as u64 >> 12, 20);
+            if imm == 0 {
+                return None;
+            }
+            imm6(0b011, rd, imm)
+        }
+        (OP, 0) if insn >> 25 == 0 && rd != 0 && rs2 != 0 => {
+            if rs1 == 0 {
+                Some(0x8002 | rd << 7 | rs2 << 2)
+            } else if rs1 == rd {
+                Some(0x9002 | rd << 7 | rs2 << 2)
+            } else {
+                None
+            }
+        }
+        (JALR, 0) if imm == 0 && rs1 != 0 && rd == 0 => Some(0x8002 | rs1 << 7),
+        (JALR, 0) if imm == 0 && rs1 != 0 && rd == 1 => Some(0x9002 | rs1 << 7),
+        (SYSTEM, _) if insn == EBREAK => Some(0x9002),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate the `lui`, `addi`, `addiw` and `slli` a `li` expands to.
+    fn run(insns: &[u32]) -> i64 {
+        let mut x = 0i64;
+        for &insn in insns {
+            let rs1 = if insn >> 15 & 0x1f == 0 { 0 } else { x };
+            let imm = sign_extend(insn as u64 >> 20, 12);
+            x = match (insn & 0x7f, insn >> 12 & 0b111) {
+                (LUI, _) => sign_extend(insn as u64 & 0xffff_f000, 32),
+                (OP_IMM, 0) => rs1.wrapping_add(imm),
+                (OP_IMM_32, 0) => rs1.wrapping_add(imm) as i32 as i64,
+                (OP_IMM, 1) => rs1 << (insn >> 20 & 0x3f),
+                _ => panic!("unexpected instruction {:#010x}", insn),
+            };
+        }
+        x
+    }
+
+    #[test]
+    fn li_sequences() {
+        // As GNU as expands them.
+        assert_eq!(li(10, 1), [0x00100513]);
+        assert_eq!(li(10, -1), [0xfff00513]);
+        assert_eq!(li(5, 4096), [0x000012b7]);
+        assert_eq!(li(10, 0x800), [0x00001537, 0x8005051b]);
+        assert_eq!(li(10, 0x7fff_ffff), [0x80000537, 0xfff5051b]);
+        assert_eq!(li(10, 1 << 32), [0x00100513, 0x02051513]);
+        assert_eq!(li(10, i64::MIN), [0xfff00513, 0x03f51513]);
+    }
+
+    #[test]
+    fn li_values() {
+        let mut values = vec![0, 1, -1, 0x7ff, 0x800, -0x800, -0x801, i64::MAX, i64::MIN];
+        for shift in 0..64 {
+            for delta in [-0x801, -1, 0, 1, 0x7ff, 0x800] {
+                values.push((1i64 << shift).wrapping_add(delta));
+                values.push((-1i64 << shift).wrapping_add(delta));
+            }
+        }
+        values.push(0x1234_5678_9abc_def0);
+        values.push(0x7fff_f800);
+        for value in values {
+            let insns = li(10, value);
+            assert!(insns.len() <= 8, "{:#x}: {:x?}", value, insns);
+            assert_eq!(run(&insns), value, "{:#x}: {:x?}", value, insns);
+        }
+    }
+
+    #[test]
+    fn compressed() {
+        // nop
+        assert_eq!(compress(0x00000013), Some(0x0001));
+        // li a0, 1
+        assert_eq!(compress(0x00100513), Some(0x4505));
+        // mv a0, a1
+        assert_eq!(compress(0x00058513), Some(0x852e));
+        // addi a0, a0, -1
+        assert_eq!(compress(0xfff50513), Some(0x157d));
+        // lui a0, 1
+        assert_eq!(compress(0x00001537), Some(0x6505));
+        // slli a0, a0, 32
+        assert_eq!(compress(0x02051513), Some(0x1502));
+        // add a0, a0, a1
+        assert_eq!(compress(0x00b50533), Some(0x952e));
+        // ret
+        assert_eq!(compress(0x00008067), Some(0x8082));
+        assert_eq!(compress(EBREAK), Some(0x9002));
+
+        // addi a0, a0, 32: the immediate doesn't fit in 6 bits
+        assert_eq!(compress(0x02050513), None);
+        // lui sp, 1 is c.addi16sp's encoding
+        assert_eq!(compress(0x00001137), None);
+        // lui a0, 0x20: out of c.lui's range
+        assert_eq!(compress(0x00020537), None);
+        // sub a0, a0, a1
+        assert_eq!(compress(0x40b50533), None);
+        // addi a0, a1, 1
+        assert_eq!(compress(0x00158513), None);
+        // slli a0, a0, 0
+        assert_eq!(compress(0x00051513), None);
+    }
+
+    #[test]
+    fn statements() {
+        let asm = Assembler::new();
+        assert_eq!(asm.assemble_statement("li t0, 4096"), Ok(vec![0x6285]));
+        assert_eq!(asm.assemble_statement("c.li a0, 1"), Ok(vec![0x4505]));
+        assert_eq!(
+            asm.assemble_statement("c.li a0, 32"),
+            Err(AsmErrorKind::ImmediateRange {
+                value: 32,
+                min: -32,
+                max: 31
+            })
+        );
+        assert_eq!(
+            asm.assemble_statement("c.mv a0, x0"),
+            Err(AsmErrorKind::NotCompressible)
+        );
+        assert_eq!(
+            Assembler { compressed: false }.assemble_statement("li t0, 4096"),
+            Ok(vec![0x000012b7])
+        );
+        assert_eq!(
+            asm.assemble_statement("frob a0"),
+            Err(AsmErrorKind::UnknownMnemonic("frob".to_string()))
+        );
+    }
+}
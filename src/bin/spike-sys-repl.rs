@@ -0,0 +1,70 @@
+//! Execute instructions one line at a time and see what they changed.
+//!
+//! ```text
+//! spike-sys-repl [--vlen 128] [--elen 64] [--mem 65536]
+//! ```
+//!
+//! Type `help` for the commands, see `spike_sys::repl`.
+use std::io::{BufRead, Write};
+
+use spike_sys::config::START_MEM;
+use spike_sys::diagnostics::{set_diagnostics, Diagnostics};
+use spike_sys::repl::Repl;
+use spike_sys::Spike;
+
+const USAGE: &str = "usage: spike-sys-repl [--vlen <bits>] [--elen <bits>] [--mem <bytes>]";
+
+fn main() {
+    let mut vlen = 128;
+    let mut elen = 64;
+    let mut mem_size = 65536;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|v| v.parse::<u64>().ok());
+        match (arg.as_str(), value) {
+            ("--vlen", Some(v)) => vlen = v as u32,
+            ("--elen", Some(v)) => elen = v as u32,
+            ("--mem", Some(v)) => mem_size = v,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    set_diagnostics(Diagnostics::Stderr);
+    let spike = match Spike::new(vlen, elen, mem_size) {
+        Ok(spike) => spike,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    println!(
+        "VLEN = {}, ELEN = {}, memory at 0x{:x}..0x{:x}; type `help` for commands",
+        vlen,
+        elen,
+        START_MEM,
+        START_MEM + mem_size
+    );
+
+    let mut repl = Repl::new(spike);
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        match line.trim() {
+            "quit" | "exit" => break,
+            line => match repl.eval(line) {
+                Ok(out) if out.is_empty() => {}
+                Ok(out) => println!("{}", out),
+                Err(e) => println!("error: {}", e),
+            },
+        }
+    }
+}
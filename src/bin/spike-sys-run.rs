@@ -0,0 +1,223 @@
+//! Run a statically linked RV64 ELF file and print the final state as JSON.
+//!
+//! ```text
+//! spike-sys-run [--isa RV64GCV] [--vlen 128] [--elen 64] [--mem <bytes>]
+//!               [--max-insns <n>] [--trace <path>] program.elf
+//! ```
+//!
+//! The program runs from its entry point with `sp` at the end of memory
+//! until it traps, e.g. at an `ecall`, or `--max-insns` instructions have
+//! retired. Memory defaults to the program's segments plus 1 MiB for the
+//! stack, see `Spike::load_elf` for where to link it. `--trace` writes a
+//! commit log in Spike's format, `-` for stderr.
+//!
+//! The exit status is 0 once the state is printed, 1 if the program couldn't
+//! be loaded and 2 for bad arguments.
+use std::fmt::Write as _;
+use std::io::Write;
+
+use spike_sys::config::{DEFAULT_ISA, START_MEM};
+use spike_sys::diagnostics::{set_diagnostics, Diagnostics};
+use spike_sys::insn::FV_CSRS;
+use spike_sys::run::{elf_extent, StopReason};
+use spike_sys::Spike;
+
+const USAGE: &str = "usage: spike-sys-run [--isa <isa>] [--vlen <bits>] [--elen <bits>] \
+                     [--mem <bytes>] [--max-insns <n>] [--trace <path>] <elf>";
+
+const STACK_SIZE: u64 = 1 << 20;
+
+struct Options {
+    isa: String,
+    vlen: u32,
+    elen: u32,
+    mem_size: Option<u64>,
+    max_insns: u64,
+    trace: Option<String>,
+    elf: String,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> T {
+    value.parse().unwrap_or_else(|_| usage())
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        isa: DEFAULT_ISA.to_string(),
+        vlen: 128,
+        elen: 64,
+        mem_size: None,
+        max_insns: 100_000_000,
+        trace: None,
+        elf: String::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if !options.elf.is_empty() {
+                usage();
+            }
+            options.elf = arg;
+            continue;
+        }
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--isa" => options.isa = value.clone(),
+            "--vlen" => options.vlen = number(&value),
+            "--elen" => options.elen = number(&value),
+            "--mem" => options.mem_size = Some(number(&value)),
+            "--max-insns" => options.max_insns = number(&value),
+            "--trace" => options.trace = Some(value.clone()),
+            _ => usage(),
+        }
+    }
+    if options.elf.is_empty() {
+        usage();
+    }
+    options
+}
+
+fn main() {
+    let options = parse_args();
+    set_diagnostics(Diagnostics::Stderr);
+
+    let elf =
+        std::fs::read(&options.elf).unwrap_or_else(|e| fail(format!("{}: {}", options.elf, e)));
+    let mem_size = match options.mem_size {
+        Some(size) => size,
+        None => {
+            let extent = elf_extent(&elf).unwrap_or_else(|e| fail(e));
+            let end = (extent.end.max(START_MEM) - START_MEM)
+                .checked_add(STACK_SIZE + 4095)
+                .unwrap_or_else(|| fail("the program leaves no room for the stack"));
+            end & !4095
+        }
+    };
+    let spike = Spike::with_isa(&options.isa, options.vlen, options.elen, mem_size)
+        .unwrap_or_else(|e| fail(e));
+    spike.load_elf(&elf).unwrap_or_else(|e| fail(e));
+    // sp
+    spike.set_xreg(2, (START_MEM + mem_size) & !15).unwrap();
+
+    let stop = match &options.trace {
+        Some(path) => {
+            let mut out: Box<dyn Write> = if path == "-" {
+                Box::new(std::io::stderr())
+            } else {
+                let file = std::fs::File::create(path)
+                    .unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+                Box::new(std::io::BufWriter::new(file))
+            };
+            spike.enable_commit_log();
+            let mut stop = StopReason::Limit { pc: spike.pc() };
+            for _ in 0..options.max_insns {
+                let (s, commit) = spike.step_traced();
+                if let Some(commit) = commit {
+                    writeln!(out, "{}", commit).unwrap_or_else(|e| fail(e));
+                }
+                stop = s;
+                if !matches!(stop, StopReason::Limit { .. }) {
+                    break;
+                }
+            }
+            out.flush().unwrap_or_else(|e| fail(e));
+            stop
+        }
+        None => spike.run_for(options.max_insns),
+    };
+    println!("{}", state(&spike, &options, &stop));
+}
+
+fn hex(x: u64) -> String {
+    format!("\"0x{:x}\"", x)
+}
+
+/// A JSON string.
+fn string(s: &str) -> String {
+    let mut r = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(r, "\\u{:04x}", c as u32).unwrap(),
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
+
+fn stop_json(stop: &StopReason) -> String {
+    let (reason, pc) = match stop {
+        StopReason::Trap { pc, error } => {
+            return match error.trap_cause() {
+                Some(cause) => format!(
+                    r#"{{"reason": "trap", "pc": {}, "cause": {}}}"#,
+                    hex(*pc),
+                    cause
+                ),
+                None => format!(
+                    r#"{{"reason": "error", "pc": {}, "message": {}}}"#,
+                    hex(*pc),
+                    string(&error.to_string())
+                ),
+            };
+        }
+        StopReason::Limit { pc } => ("limit", pc),
+        StopReason::Breakpoint { pc } => ("breakpoint", pc),
+        StopReason::Watchpoint { pc, .. } => ("watchpoint", pc),
+        StopReason::Condition { pc, .. } => ("condition", pc),
+    };
+    format!(r#"{{"reason": "{}", "pc": {}}}"#, reason, hex(*pc))
+}
+
+/// The final state, one field per line. Register values are hex strings,
+/// vector registers big endian like Spike's commit log.
+fn state(spike: &Spike, options: &Options, stop: &StopReason) -> String {
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, r#"  "isa": {},"#, string(&options.isa)).unwrap();
+    writeln!(out, r#"  "vlen": {},"#, options.vlen).unwrap();
+    writeln!(out, r#"  "elen": {},"#, options.elen).unwrap();
+    writeln!(out, r#"  "stop": {},"#, stop_json(stop)).unwrap();
+    writeln!(out, r#"  "retired": {},"#, spike.retired()).unwrap();
+    writeln!(out, r#"  "pc": {},"#, hex(spike.pc())).unwrap();
+
+    let x: Vec<String> = (0..32).map(|i| hex(spike.get_xreg(i).unwrap())).collect();
+    writeln!(out, r#"  "x": [{}],"#, x.join(", ")).unwrap();
+    let f: Vec<String> = (0..32).map(|i| hex(spike.get_freg(i).unwrap())).collect();
+    writeln!(out, r#"  "f": [{}],"#, f.join(", ")).unwrap();
+    // CSRs that can't be read, e.g. fcsr while the FPU is off, are null.
+    let csrs: Vec<String> = FV_CSRS
+        .iter()
+        .map(|(name, csr)| {
+            let value = spike.get_csr(*csr as u64).map_or("null".to_string(), hex);
+            format!("{}: {}", string(name), value)
+        })
+        .collect();
+    writeln!(out, r#"  "csr": {{{}}},"#, csrs.join(", ")).unwrap();
+
+    let vlenb = spike.get_vlen() / 8;
+    let mut file = vec![0; 32 * vlenb as usize];
+    spike.get_vreg(0, &mut file).unwrap();
+    let v: Vec<String> = file
+        .chunks(vlenb as usize)
+        .map(|reg| {
+            let digits: String = reg.iter().rev().map(|b| format!("{:02x}", b)).collect();
+            format!("\"0x{}\"", digits)
+        })
+        .collect();
+    writeln!(out, r#"  "v": [{}]"#, v.join(", ")).unwrap();
+    write!(out, "}}").unwrap();
+    out
+}
@@ -0,0 +1,74 @@
+//! Compare two commit logs and show where they first diverge.
+//!
+//! ```text
+//! spike-sys-tracediff [--context <n>] [--start <left>,<right>] left.log right.log
+//! ```
+//!
+//! The logs are in the format of `spike --log-commits`, as written by
+//! `spike-sys-run --trace`. They're aligned at the pc one of them starts at,
+//! or at the given instruction indices with `--start`.
+//!
+//! Like diff(1), the exit status is 0 if the logs agree, 1 if they diverge
+//! and 2 for bad arguments or unreadable logs.
+use spike_sys::disasm::Disassembler;
+use spike_sys::trace::{parse, Commit};
+use spike_sys::tracediff::{align, diff};
+
+const USAGE: &str =
+    "usage: spike-sys-tracediff [--context <n>] [--start <left>,<right>] <left> <right>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(2);
+}
+
+fn read(path: &str) -> Vec<Commit> {
+    let log = std::fs::read_to_string(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+    parse(&log).unwrap_or_else(|e| fail(format!("{}: {}", path, e)))
+}
+
+fn main() {
+    let mut context = 5;
+    let mut start = None;
+    let mut paths = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--context" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                context = value.parse().unwrap_or_else(|_| usage());
+            }
+            "--start" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                let (l, r) = value.split_once(',').unwrap_or_else(|| usage());
+                let l = l.parse().unwrap_or_else(|_| usage());
+                let r = r.parse().unwrap_or_else(|_| usage());
+                start = Some((l, r));
+            }
+            _ if arg.starts_with("--") => usage(),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        usage();
+    }
+
+    let left = read(&paths[0]);
+    let right = read(&paths[1]);
+    let start = start
+        .or_else(|| align(&left, &right))
+        .unwrap_or_else(|| fail("the logs have no pc in common to start at"));
+    match diff(&left, &right, start) {
+        None => println!("{} instructions agree", left.len().saturating_sub(start.0)),
+        Some(divergence) => {
+            let disasm = Disassembler::new(64).unwrap_or_else(|e| fail(e));
+            println!("{}", divergence.report(&left, &right, context, &disasm));
+            std::process::exit(1);
+        }
+    }
+}
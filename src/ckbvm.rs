@@ -0,0 +1,197 @@
+//! An adapter that drives a ckb-vm `DefaultMachine` through the [`Model`] interface.
+//!
+//! Only available with the `ckb-vm` feature.
+pub use ckb_vm;
+
+use ckb_vm::instructions::Instruction;
+use ckb_vm::instructions::{
+    execute_instruction, generate_handle_function_list, generate_vcheck_function_list,
+};
+use ckb_vm::{
+    CoreMachine, DefaultCoreMachine, DefaultMachine, DefaultMachineBuilder, Memory, SparseMemory,
+};
+
+use crate::model::{sync_memory, sync_state, SyncError};
+use crate::{Model, Spike};
+
+/// ckb-vm's rvv branch has a fixed VLEN.
+pub const VLEN: usize = 128;
+
+pub type CkbMachine = DefaultMachine<'static, DefaultCoreMachine<u64, SparseMemory<u64>>>;
+
+/// The error of the [`Model`] methods: ckb-vm's own, or a register index
+/// ckb-vm would panic on.
+#[derive(Debug)]
+pub enum CkbVmError {
+    Vm(ckb_vm::Error),
+    InvalidRegister(usize),
+}
+
+impl std::fmt::Display for CkbVmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CkbVmError::Vm(e) => e.fmt(f),
+            CkbVmError::InvalidRegister(index) => write!(f, "invalid register {}", index),
+        }
+    }
+}
+
+impl std::error::Error for CkbVmError {}
+
+impl From<ckb_vm::Error> for CkbVmError {
+    fn from(e: ckb_vm::Error) -> Self {
+        CkbVmError::Vm(e)
+    }
+}
+
+/// There are 32 x and 32 v registers.
+fn check_register(index: usize) -> Result<(), CkbVmError> {
+    if index < 32 {
+        Ok(())
+    } else {
+        Err(CkbVmError::InvalidRegister(index))
+    }
+}
+
+type Executor = Box<dyn Fn(&mut CkbMachine, Instruction) -> Result<(), ckb_vm::Error>>;
+
+pub struct CkbVm {
+    machine: CkbMachine,
+    executor: Executor,
+}
+
+impl CkbVm {
+    pub fn new() -> Self {
+        let machine =
+            DefaultMachineBuilder::new(DefaultCoreMachine::<u64, SparseMemory<u64>>::new(
+                ckb_vm::ISA_IMC | ckb_vm::ISA_B | ckb_vm::ISA_V,
+                ckb_vm::machine::VERSION1,
+                u64::MAX,
+            ))
+            .build();
+        // The function lists are large, build them once per machine rather than per instruction.
+        let vcheck_function_list = generate_vcheck_function_list();
+        let handle_function_list = generate_handle_function_list();
+        let executor: Executor = Box::new(move |machine, insn| {
+            execute_instruction(machine, &vcheck_function_list, &handle_function_list, insn)
+        });
+        Self { machine, executor }
+    }
+
+    pub fn machine(&self) -> &CkbMachine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut CkbMachine {
+        &mut self.machine
+    }
+
+    /// Copy registers, vtype and vl from `spike` into this machine.
+    pub fn sync_from(&mut self, spike: &mut Spike) -> Result<(), SyncError<Spike, CkbVm>> {
+        sync_state(spike, self)
+    }
+
+    /// Copy registers, vtype and vl from this machine into `spike`.
+    pub fn sync_to(&mut self, spike: &mut Spike) -> Result<(), SyncError<CkbVm, Spike>> {
+        sync_state(self, spike)
+    }
+
+    /// Copy `len` bytes of memory at `addr` from `spike` into this machine.
+    pub fn sync_memory_from(
+        &mut self,
+        spike: &mut Spike,
+        addr: u64,
+        len: usize,
+    ) -> Result<(), SyncError<Spike, CkbVm>> {
+        sync_memory(spike, self, addr, len)
+    }
+
+    /// Copy `len` bytes of memory at `addr` from this machine into `spike`.
+    pub fn sync_memory_to(
+        &mut self,
+        spike: &mut Spike,
+        addr: u64,
+        len: usize,
+    ) -> Result<(), SyncError<CkbVm, Spike>> {
+        sync_memory(self, spike, addr, len)
+    }
+}
+
+impl Default for CkbVm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Model for CkbVm {
+    type Error = CkbVmError;
+
+    fn execute(&mut self, insn: u32) -> Result<(), CkbVmError> {
+        let insn = ckb_vm::instructions::v::factory::<u64>(insn, ckb_vm::machine::VERSION1)
+            .ok_or(ckb_vm::Error::InvalidInstruction(insn))?;
+        Ok((self.executor)(&mut self.machine, insn)?)
+    }
+
+    fn vlen(&self) -> u64 {
+        VLEN as u64
+    }
+
+    fn vl(&self) -> u64 {
+        self.machine.coprocessor_v().vl()
+    }
+
+    fn sew(&self) -> u64 {
+        self.machine.coprocessor_v().vsew()
+    }
+
+    fn vtype(&self) -> u64 {
+        self.machine.coprocessor_v().vtype()
+    }
+
+    fn vill(&self) -> bool {
+        self.machine.coprocessor_v().vill()
+    }
+
+    fn xreg(&self, index: usize) -> Result<u64, CkbVmError> {
+        check_register(index)?;
+        Ok(self.machine.registers()[index])
+    }
+
+    fn set_xreg(&mut self, index: usize, value: u64) -> Result<(), CkbVmError> {
+        check_register(index)?;
+        self.machine.set_register(index, value);
+        Ok(())
+    }
+
+    fn vreg(&mut self, index: usize, buf: &mut [u8]) -> Result<(), CkbVmError> {
+        check_register(index)?;
+        buf.copy_from_slice(
+            self.machine
+                .coprocessor_v_mut()
+                .element_ref(index, VLEN as u64, 0),
+        );
+        Ok(())
+    }
+
+    fn set_vreg(&mut self, index: usize, buf: &[u8]) -> Result<(), CkbVmError> {
+        check_register(index)?;
+        self.machine
+            .coprocessor_v_mut()
+            .element_mut(index, VLEN as u64, 0)
+            .copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn load_mem(&mut self, addr: u64, buf: &mut [u8]) -> Result<(), CkbVmError> {
+        let bytes = self
+            .machine
+            .memory_mut()
+            .load_bytes(addr, buf.len() as u64)?;
+        buf.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn store_mem(&mut self, addr: u64, buf: &[u8]) -> Result<(), CkbVmError> {
+        Ok(self.machine.memory_mut().store_bytes(addr, buf)?)
+    }
+}
@@ -0,0 +1,108 @@
+//! Checks on the arguments of [`Spike::new`](crate::Spike::new), so that a
+//! configuration Spike can't handle is reported before it reaches Spike.
+use crate::Error;
+
+/// The address memory starts at, see `rvv_new_processor`.
+pub const START_MEM: u64 = 4096;
+
+/// The ISA of processors created by [`Spike::new`](crate::Spike::new).
+pub const DEFAULT_ISA: &str = "RV64GCV";
+
+/// Spike's physical addresses have at most 56 bits, memory ending above that
+/// can't be reached.
+pub const MAX_MEM_SIZE: u64 = (1 << 56) - START_MEM;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    VlenNotPowerOfTwo(u32),
+    /// VLEN outside 32..=65536.
+    VlenOutOfRange(u32),
+    ElenNotPowerOfTwo(u32),
+    /// ELEN outside 32..=1024.
+    ElenOutOfRange(u32),
+    ElenExceedsVlen {
+        vlen: u32,
+        elen: u32,
+    },
+    /// More than [`MAX_MEM_SIZE`] bytes of memory.
+    MemSizeTooLarge(u64),
+    /// An ISA string that isn't RV64 with the V extension, which the shim
+    /// assumes.
+    UnsupportedIsa(String),
+    /// Rejected by the shim or Spike despite passing the checks above, or
+    /// memory couldn't be allocated.
+    Spike(Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::VlenNotPowerOfTwo(vlen) => {
+                write!(f, "VLEN {} is not a power of two", vlen)
+            }
+            ConfigError::VlenOutOfRange(vlen) => {
+                write!(f, "VLEN {} is outside 32..=65536", vlen)
+            }
+            ConfigError::ElenNotPowerOfTwo(elen) => {
+                write!(f, "ELEN {} is not a power of two", elen)
+            }
+            ConfigError::ElenOutOfRange(elen) => write!(f, "ELEN {} is outside 32..=1024", elen),
+            ConfigError::ElenExceedsVlen { vlen, elen } => {
+                write!(f, "ELEN {} is greater than VLEN {}", elen, vlen)
+            }
+            ConfigError::MemSizeTooLarge(size) => write!(
+                f,
+                "memory size {:#x} is greater than the maximum {:#x}",
+                size, MAX_MEM_SIZE
+            ),
+            ConfigError::UnsupportedIsa(isa) => {
+                write!(f, "ISA {:?} is not RV64 with the V extension", isa)
+            }
+            ConfigError::Spike(e) => write!(f, "rejected by Spike: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<Error> for ConfigError {
+    fn from(e: Error) -> Self {
+        ConfigError::Spike(e)
+    }
+}
+
+/// Check a configuration without creating a processor. `mem_size` 0 means no
+/// memory.
+pub fn check(vlen: u32, elen: u32, mem_size: u64) -> Result<(), ConfigError> {
+    if !vlen.is_power_of_two() {
+        return Err(ConfigError::VlenNotPowerOfTwo(vlen));
+    }
+    if !(32..=65536).contains(&vlen) {
+        return Err(ConfigError::VlenOutOfRange(vlen));
+    }
+    if !elen.is_power_of_two() {
+        return Err(ConfigError::ElenNotPowerOfTwo(elen));
+    }
+    if !(32..=1024).contains(&elen) {
+        return Err(ConfigError::ElenOutOfRange(elen));
+    }
+    if elen > vlen {
+        return Err(ConfigError::ElenExceedsVlen { vlen, elen });
+    }
+    if mem_size > MAX_MEM_SIZE {
+        return Err(ConfigError::MemSizeTooLarge(mem_size));
+    }
+    Ok(())
+}
+
+/// Check that `isa` names RV64 with the V extension, e.g. `RV64GCV` or
+/// `rv64imacv_zba`. Whether Spike knows every extension is only found out by
+/// creating a processor.
+pub fn check_isa(isa: &str) -> Result<(), ConfigError> {
+    let lower = isa.to_ascii_lowercase();
+    let base = lower.split('_').next().unwrap();
+    match base.strip_prefix("rv64") {
+        Some(letters) if letters.contains('v') && !isa.contains('\0') => Ok(()),
+        _ => Err(ConfigError::UnsupportedIsa(isa.to_string())),
+    }
+}
@@ -0,0 +1,143 @@
+//! What a processor executed: the number of retired instructions and how often
+//! each instruction retired, overall and under each SEW and LMUL.
+//!
+//! Instructions are named as in Spike's opcode table with dots, e.g.
+//! `vadd.vv`, which is also how the disassembler prints them.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::decode::{decode, spike_opcodes, Category, SpikeOpcode};
+use crate::disasm::Disassembler;
+use crate::ffi::{rvv_get_coverage, rvv_get_retired, rvv_set_coverage};
+use crate::vtype::{Lmul, VType};
+use crate::{Error, Spike};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Instructions retired, including those outside the V extension.
+    pub retired: u64,
+    /// Retired instructions by mnemonic.
+    pub mnemonics: BTreeMap<String, u64>,
+    /// Retired vector instructions by mnemonic, SEW and LMUL. Instructions
+    /// executed while `vill` was set are only counted in `mnemonics`.
+    pub vtypes: BTreeMap<(String, u32, Lmul), u64>,
+}
+
+impl Coverage {
+    /// Add the counts of `other`, e.g. from another processor or fuzz run.
+    pub fn merge(&mut self, other: &Coverage) {
+        self.retired += other.retired;
+        for (mnemonic, n) in &other.mnemonics {
+            *self.mnemonics.entry(mnemonic.clone()).or_insert(0) += n;
+        }
+        for (key, n) in &other.vtypes {
+            *self.vtypes.entry(key.clone()).or_insert(0) += n;
+        }
+    }
+
+    /// Vector instructions supported by the processors' RV64GCV that never
+    /// retired, by category.
+    pub fn never_executed(&self) -> Result<BTreeMap<Category, Vec<String>>, Error> {
+        let mut r: BTreeMap<Category, Vec<String>> = BTreeMap::new();
+        for (category, name) in rvv_instructions()? {
+            if !self.mnemonics.contains_key(&name) {
+                r.entry(category).or_default().push(name);
+            }
+        }
+        Ok(r)
+    }
+
+    /// A table of the vector instructions never executed by category, with
+    /// the number executed out of those supported.
+    pub fn report(&self) -> Result<String, Error> {
+        let mut total: BTreeMap<Category, usize> = BTreeMap::new();
+        for (category, _) in rvv_instructions()? {
+            *total.entry(category).or_insert(0) += 1;
+        }
+        let never = self.never_executed()?;
+
+        let mut out = String::new();
+        writeln!(out, "{:<12} {:>9}  never executed", "category", "executed").unwrap();
+        for (category, total) in &total {
+            let missing = never.get(category).map_or(&[][..], |v| &v[..]);
+            let executed = format!("{}/{}", total - missing.len(), total);
+            write!(out, "{:<12} {:>9}", format!("{:?}", category), executed).unwrap();
+            for (i, names) in missing.chunks(6).enumerate() {
+                let indent = if i == 0 { 0 } else { 22 };
+                writeln!(out, "{:indent$}  {}", "", names.join(" "), indent = indent).unwrap();
+            }
+            if missing.is_empty() {
+                writeln!(out).unwrap();
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// The vector entries of Spike's opcode table the disassembler knows for
+/// RV64GCV, leaving out extensions like Zvbb the processors don't have.
+fn rvv_instructions() -> Result<BTreeSet<(Category, String)>, Error> {
+    let dis = Disassembler::new(64)?;
+    let known = |op: &SpikeOpcode| match dis.disassemble(op.bits as u64) {
+        Ok(text) => !text.contains("unknown"),
+        Err(_) => false,
+    };
+    Ok(spike_opcodes()
+        .into_iter()
+        .filter(|op| op.name.starts_with('v') && known(op))
+        .filter_map(|op| decode(op.bits).map(|insn| (insn.category, op.name)))
+        .collect())
+}
+
+impl Spike {
+    /// Count instructions for [`coverage`](Self::coverage). Off for new
+    /// processors; kept by [`reset`](Self::reset), which clears the counts.
+    pub fn set_coverage(&self, enabled: bool) {
+        unsafe { rvv_set_coverage(self.addr, enabled as i32) }
+    }
+
+    /// The number of instructions retired since creation or the last reset.
+    /// Counted whether or not coverage is enabled.
+    pub fn retired(&self) -> u64 {
+        unsafe { rvv_get_retired(self.addr) }
+    }
+
+    /// Coverage since creation or the last reset. Empty apart from `retired`
+    /// unless enabled with [`set_coverage`](Self::set_coverage).
+    pub fn coverage(&self) -> Coverage {
+        let null = std::ptr::null_mut();
+        let n = unsafe { rvv_get_coverage(self.addr, null, null, null, 0) } as usize;
+        let mut insns = vec![0; n];
+        let mut vtypes = vec![0; n];
+        let mut counts = vec![0; n];
+        unsafe {
+            rvv_get_coverage(
+                self.addr,
+                insns.as_mut_ptr(),
+                vtypes.as_mut_ptr(),
+                counts.as_mut_ptr(),
+                n as u64,
+            )
+        };
+
+        let mut r = Coverage {
+            retired: self.retired(),
+            ..Coverage::default()
+        };
+        for ((insn, vtype), count) in insns.iter().zip(&vtypes).zip(&counts) {
+            let insn = match decode(*insn as u32) {
+                Some(insn) => insn,
+                None => continue,
+            };
+            *r.mnemonics.entry(insn.mnemonic.clone()).or_insert(0) += count;
+            if insn.category == Category::Scalar {
+                continue;
+            }
+            if let Some(vtype) = VType::from_bits(*vtype) {
+                let key = (insn.mnemonic, vtype.sew, vtype.lmul);
+                *r.vtypes.entry(key).or_insert(0) += count;
+            }
+        }
+        r
+    }
+}
@@ -0,0 +1,250 @@
+//! Instruction decoding against Spike's opcode table.
+//!
+//! The mnemonic and length come from Spike (`riscv/encoding.h` and `insn_t`);
+//! operands are extracted from the fields the matching opcode leaves variable.
+//! Operands are only decoded for vector instructions.
+use std::ffi::CStr;
+
+use crate::ffi::{rvv_decode, rvv_opcode, rvv_opcode_count};
+use crate::insn::{FReg, VReg, XReg, OPFVF, OPFVV, OPIVV, OPIVX, OPMVV, OPMVX};
+use crate::opcodes;
+
+const LOAD_FP: u32 = 0b0000111;
+const STORE_FP: u32 = 0b0100111;
+const OP_V: u32 = 0b1010111;
+const OPCFG: u32 = 0b111;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Load,
+    Store,
+    /// Integer and fixed-point arithmetic, including widening, narrowing and reductions.
+    Arith,
+    /// Comparisons and instructions reading or writing masks.
+    Mask,
+    /// Slides, gathers, compress and moves between register files.
+    Permutation,
+    Fp,
+    /// `vsetvli`, `vsetivli` and `vsetvl`.
+    Config,
+    /// Anything outside the V extension.
+    Scalar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Vd(VReg),
+    /// The data register of a store.
+    Vs3(VReg),
+    Vs2(VReg),
+    Vs1(VReg),
+    Rd(XReg),
+    Rs1(XReg),
+    Rs2(XReg),
+    Fd(FReg),
+    Fs1(FReg),
+    Imm(i64),
+    /// The raw `vm` bit: 0 means masked by v0.
+    Vm(u32),
+    /// `nf` as encoded, the number of fields minus one.
+    Nf(u32),
+    /// EEW of a load or store in bits.
+    Width(u32),
+}
+
+/// An entry of Spike's opcode table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpikeOpcode {
+    /// The mnemonic with dots, e.g. `vadd.vv`.
+    pub name: String,
+    pub bits: u32,
+    pub mask: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedInsn {
+    pub bits: u32,
+    pub mnemonic: String,
+    /// Length in bytes.
+    pub length: usize,
+    pub category: Category,
+    pub operands: Vec<Operand>,
+    /// The fixed bits of the matching opcode.
+    pub mask: u32,
+}
+
+impl DecodedInsn {
+    /// The `nf` field if it is an operand, 0 otherwise (including whole
+    /// register loads and stores, where it's part of the mnemonic).
+    pub fn nf(&self) -> u32 {
+        self.operands
+            .iter()
+            .find_map(|o| match o {
+                Operand::Nf(nf) => Some(*nf),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    /// Whether the instruction is masked by v0.
+    pub fn masked(&self) -> bool {
+        self.operands.contains(&Operand::Vm(0))
+    }
+}
+
+fn opcode(index: u32) -> Option<SpikeOpcode> {
+    let mut name = std::ptr::null();
+    let mut bits = 0;
+    let mut mask = 0;
+    if unsafe { rvv_opcode(index, &mut name, &mut bits, &mut mask) } != 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .replace('_', ".");
+    Some(SpikeOpcode { name, bits, mask })
+}
+
+/// Every instruction known to Spike.
+pub fn spike_opcodes() -> Vec<SpikeOpcode> {
+    let n = unsafe { rvv_opcode_count() };
+    (0..n).filter_map(opcode).collect()
+}
+
+/// Decode `insn` as an RV64 instruction. Returns `None` if Spike doesn't know
+/// the encoding.
+pub fn decode(insn: u32) -> Option<DecodedInsn> {
+    let mut index = 0;
+    let mut length = 0;
+    if unsafe { rvv_decode(insn as u64, &mut index, &mut length) } != 0 {
+        return None;
+    }
+    let opcode = opcode(index)?;
+    let category = category(&opcode.name, insn);
+    let operands = if opcode.name.starts_with('v') {
+        operands(&opcode.name, insn, opcode.mask)
+    } else {
+        vec![]
+    };
+    Some(DecodedInsn {
+        bits: insn,
+        mnemonic: opcode.name,
+        length: length as usize,
+        category,
+        operands,
+        mask: opcode.mask,
+    })
+}
+
+fn category(name: &str, insn: u32) -> Category {
+    if !name.starts_with('v') {
+        return Category::Scalar;
+    }
+    let funct3 = insn >> 12 & 0b111;
+    match insn & 0x7f {
+        LOAD_FP => return Category::Load,
+        STORE_FP => return Category::Store,
+        _ => {}
+    }
+    if funct3 == OPCFG {
+        return Category::Config;
+    }
+    if funct3 == OPFVV || funct3 == OPFVF {
+        return Category::Fp;
+    }
+    match opcodes::find(insn).map(|o| o.class) {
+        Some(opcodes::InsnClass::Mask) => Category::Mask,
+        Some(opcodes::InsnClass::Permutation) => Category::Permutation,
+        _ => Category::Arith,
+    }
+}
+
+fn operands(name: &str, insn: u32, mask: u32) -> Vec<Operand> {
+    let free = |lo: u32, n: u32| mask >> lo & ((1 << n) - 1) == 0;
+    let rd = insn >> 7 & 0b11111;
+    let rs1 = insn >> 15 & 0b11111;
+    let rs2 = insn >> 20 & 0b11111;
+    let funct3 = insn >> 12 & 0b111;
+    let v = |n: u32| VReg::new(n).unwrap();
+    let x = |n: u32| XReg::new(n).unwrap();
+    let mut r = vec![];
+
+    let opcode = insn & 0x7f;
+    if opcode == LOAD_FP || opcode == STORE_FP {
+        r.push(if opcode == LOAD_FP {
+            Operand::Vd(v(rd))
+        } else {
+            Operand::Vs3(v(rd))
+        });
+        r.push(Operand::Rs1(x(rs1)));
+        if free(20, 5) {
+            match insn >> 26 & 0b11 {
+                0b10 => r.push(Operand::Rs2(x(rs2))),
+                _ => r.push(Operand::Vs2(v(rs2))),
+            }
+        }
+        if free(25, 1) {
+            r.push(Operand::Vm(insn >> 25 & 1));
+        }
+        if free(29, 3) {
+            r.push(Operand::Nf(insn >> 29));
+        }
+        let width = [8, 0, 0, 0, 0, 16, 32, 64][funct3 as usize];
+        r.push(Operand::Width(width << ((insn >> 28 & 1) * 4)));
+        return r;
+    }
+    if opcode != OP_V {
+        return r;
+    }
+
+    if funct3 == OPCFG {
+        r.push(Operand::Rd(x(rd)));
+        match name {
+            "vsetvli" => {
+                r.push(Operand::Rs1(x(rs1)));
+                r.push(Operand::Imm((insn >> 20 & 0x7ff) as i64));
+            }
+            "vsetivli" => {
+                r.push(Operand::Imm(rs1 as i64));
+                r.push(Operand::Imm((insn >> 20 & 0x3ff) as i64));
+            }
+            _ => {
+                r.push(Operand::Rs1(x(rs1)));
+                r.push(Operand::Rs2(x(rs2)));
+            }
+        }
+        return r;
+    }
+
+    if free(7, 5) {
+        // vmv.x.s, vcpop.m, vfirst.m and vfmv.f.s write a scalar register.
+        let scalar = insn >> 26 == 0b010000;
+        r.push(match funct3 {
+            OPMVV if scalar => Operand::Rd(x(rd)),
+            OPFVV if scalar => Operand::Fd(FReg::new(rd).unwrap()),
+            _ => Operand::Vd(v(rd)),
+        });
+    }
+    if free(20, 5) {
+        r.push(Operand::Vs2(v(rs2)));
+    }
+    if free(15, 5) {
+        r.push(match funct3 {
+            OPIVV | OPMVV | OPFVV => Operand::Vs1(v(rs1)),
+            OPIVX | OPMVX => Operand::Rs1(x(rs1)),
+            OPFVF => Operand::Fs1(FReg::new(rs1).unwrap()),
+            _ => {
+                let uimm = opcodes::find(insn).map(|o| o.vs1) == Some(opcodes::Operand::Uimm5);
+                if uimm {
+                    Operand::Imm(rs1 as i64)
+                } else {
+                    Operand::Imm(((rs1 as i64) << 59) >> 59)
+                }
+            }
+        });
+    }
+    if free(25, 1) {
+        r.push(Operand::Vm(insn >> 25 & 1));
+    }
+    r
+}
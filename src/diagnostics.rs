@@ -0,0 +1,64 @@
+//! Where the shim's and Spike's diagnostics go.
+//!
+//! Bounds errors from the shim and everything Spike writes to a processor's
+//! log stream are passed to Rust one line at a time. By default they are
+//! logged with the `log` crate under the `spike` target; they can also be
+//! written to stderr as before, or dropped, e.g. when fuzzing.
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Once;
+
+use crate::ffi::{rvv_set_log_callback, RVV_LOG_ERROR, RVV_LOG_WARN};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diagnostics {
+    /// Log with the `log` crate, target `spike`.
+    Log,
+    Stderr,
+    Silent,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(Diagnostics::Log as u8);
+
+/// Set where diagnostics go, for all processors.
+pub fn set_diagnostics(mode: Diagnostics) {
+    install();
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn diagnostics() -> Diagnostics {
+    match MODE.load(Ordering::Relaxed) {
+        0 => Diagnostics::Log,
+        1 => Diagnostics::Stderr,
+        _ => Diagnostics::Silent,
+    }
+}
+
+pub(crate) fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| unsafe { rvv_set_log_callback(Some(callback)) });
+}
+
+extern "C" fn callback(level: i32, message: *const c_char) {
+    // A panicking logger must not unwind into the shim.
+    let _ = std::panic::catch_unwind(|| log(level, message));
+}
+
+fn log(level: i32, message: *const c_char) {
+    let mode = diagnostics();
+    if mode == Diagnostics::Silent {
+        return;
+    }
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    if mode == Diagnostics::Stderr {
+        eprintln!("{}", message);
+        return;
+    }
+    let level = match level as u32 {
+        RVV_LOG_ERROR => log::Level::Error,
+        RVV_LOG_WARN => log::Level::Warn,
+        _ => log::Level::Info,
+    };
+    log::log!(target: "spike", level, "{}", message);
+}
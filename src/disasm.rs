@@ -0,0 +1,340 @@
+//! Disassembly of instruction streams and ELF files using Spike's disassembler.
+//!
+//! Spike's output can be rewritten according to [`Options`]: numeric register
+//! names (`x5` instead of `t0`, matching ckb-vm's logs), a fixed immediate
+//! radix, and absolute branch and jump targets labelled with symbols.
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::fmt::Write;
+use std::os::raw::c_char;
+
+use goblin::elf::{section_header::SHT_NOBITS, Elf};
+
+use crate::ffi::{rvv_delete_disassembler, rvv_disassemble, rvv_new_disassembler};
+use crate::insn::{FREG_NAMES, XREG_NAMES};
+use crate::Error;
+
+/// The length in bytes of the instruction whose lowest 16 bits are `parcel`,
+/// following the same rules as Spike: 2 for RVC, then 4, 6 and 8.
+pub fn insn_length(parcel: u16) -> usize {
+    if parcel & 0b11 != 0b11 {
+        2
+    } else if parcel & 0x1f != 0x1f {
+        4
+    } else if parcel & 0x3f != 0x3f {
+        6
+    } else if parcel & 0x7f != 0x7f {
+        8
+    } else {
+        4
+    }
+}
+
+#[derive(Debug)]
+pub enum ElfError {
+    Parse(goblin::error::Error),
+    /// The section doesn't exist or has no contents in the file.
+    MissingSection(String),
+}
+
+impl std::fmt::Display for ElfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElfError::Parse(e) => write!(f, "invalid ELF: {}", e),
+            ElfError::MissingSection(name) => write!(f, "no {} section", name),
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+impl From<goblin::error::Error> for ElfError {
+    fn from(e: goblin::error::Error) -> Self {
+        ElfError::Parse(e)
+    }
+}
+
+/// How immediates are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Immediates {
+    /// As Spike prints them: decimal, except for `lui`, `auipc` and branch
+    /// offsets.
+    Spike,
+    Decimal,
+    Hex,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// ABI register names (`t0`, `fa0`) if set, otherwise `x5`, `f10`.
+    pub abi_names: bool,
+    pub immediates: Immediates,
+    /// Symbols used to label branch and jump targets. Targets are only
+    /// resolved when the instruction's address is known.
+    pub symbols: BTreeMap<u64, String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            abi_names: true,
+            immediates: Immediates::Spike,
+            symbols: BTreeMap::new(),
+        }
+    }
+}
+
+pub struct Disassembler {
+    addr: u64,
+    pub options: Options,
+}
+
+impl Disassembler {
+    /// A disassembler for RV32GCV if `xlen` is 32 or RV64GCV if it's 64.
+    pub fn new(xlen: u32) -> Result<Self, Error> {
+        let mut addr = 0;
+        let r = unsafe { rvv_new_disassembler(xlen, &mut addr) };
+        if r != 0 {
+            return Err(Error::from_code(r));
+        }
+        Ok(Self {
+            addr,
+            options: Options::default(),
+        })
+    }
+
+    /// Disassemble a single instruction. Branch and jump targets stay
+    /// relative to pc.
+    pub fn disassemble(&self, insn: u64) -> Result<String, Error> {
+        self.disassemble_spike(insn)
+            .map(|text| self.rewrite(&text, None, &self.options.symbols))
+    }
+
+    /// Disassemble the instruction at `addr`, resolving branch and jump
+    /// targets.
+    pub fn disassemble_at(&self, insn: u64, addr: u64) -> Result<String, Error> {
+        self.disassemble_spike(insn)
+            .map(|text| self.rewrite(&text, Some(addr), &self.options.symbols))
+    }
+
+    fn disassemble_spike(&self, insn: u64) -> Result<String, Error> {
+        let mut output = [0u8; 128];
+        let mut output_len = output.len() as u32;
+        let r = unsafe {
+            rvv_disassemble(
+                self.addr,
+                insn,
+                output.as_mut_ptr() as *mut c_char,
+                &mut output_len,
+            )
+        };
+        if r != 0 {
+            return Err(Error::from_code(r));
+        }
+        let text = unsafe { CStr::from_ptr(output.as_ptr() as *const c_char) };
+        Ok(text.to_string_lossy().into_owned())
+    }
+
+    /// Disassemble a little endian instruction stream starting at `base_addr`,
+    /// returning `(addr, raw, text)` for each instruction. The stream may mix
+    /// 16 and 32 bit instructions; a truncated instruction at the end is
+    /// reported as `(bad)`.
+    pub fn disassemble_buffer(&self, bytes: &[u8], base_addr: u64) -> Vec<(u64, u64, String)> {
+        self.walk(bytes, base_addr, &self.options.symbols)
+    }
+
+    fn walk(
+        &self,
+        bytes: &[u8],
+        base_addr: u64,
+        symbols: &BTreeMap<u64, String>,
+    ) -> Vec<(u64, u64, String)> {
+        let mut r = vec![];
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            let addr = base_addr + offset as u64;
+            let len = if rest.len() < 2 {
+                rest.len()
+            } else {
+                insn_length(u16::from_le_bytes([rest[0], rest[1]]))
+            };
+            let raw = rest
+                .iter()
+                .take(len)
+                .rev()
+                .fold(0u64, |acc, b| acc << 8 | *b as u64);
+            let text = if rest.len() < len || len < 2 {
+                "(bad)".to_string()
+            } else {
+                self.disassemble_spike(raw)
+                    .map(|text| self.rewrite(&text, Some(addr), symbols))
+                    .unwrap_or_else(|_| "(bad)".to_string())
+            };
+            r.push((addr, raw, text));
+            offset += std::cmp::min(len, rest.len());
+        }
+        r
+    }
+
+    /// An objdump-like listing of the `.text` section of an ELF file, with
+    /// symbol labels. The ELF's symbols are used if `options.symbols` is empty.
+    pub fn disassemble_elf(&self, elf: &[u8]) -> Result<String, ElfError> {
+        let parsed = Elf::parse(elf)?;
+        let section = parsed
+            .section_headers
+            .iter()
+            .find(|sh| {
+                parsed.shdr_strtab.get_at(sh.sh_name) == Some(".text") && sh.sh_type != SHT_NOBITS
+            })
+            .ok_or_else(|| ElfError::MissingSection(".text".to_string()))?;
+        let range = section
+            .file_range()
+            .filter(|r| r.end <= elf.len())
+            .ok_or_else(|| ElfError::MissingSection(".text".to_string()))?;
+        let symbols = if self.options.symbols.is_empty() {
+            symbols(&parsed)
+        } else {
+            self.options.symbols.clone()
+        };
+
+        let mut out = String::new();
+        writeln!(out, "Disassembly of section .text:").unwrap();
+        for (addr, raw, text) in self.walk(&elf[range], section.sh_addr, &symbols) {
+            if let Some(name) = symbols.get(&addr) {
+                writeln!(out, "\n{:016x} <{}>:", addr, name).unwrap();
+            }
+            let raw = format!("{:0width$x}", raw, width = insn_length(raw as u16) * 2);
+            writeln!(out, "{:8x}:\t{:<16}\t{}", addr, raw, text).unwrap();
+        }
+        Ok(out)
+    }
+}
+
+impl Disassembler {
+    fn rewrite(&self, text: &str, addr: Option<u64>, symbols: &BTreeMap<u64, String>) -> String {
+        // Spike prints branch and jump targets as `pc + 0x8`.
+        let (text, target) = match (text.find("pc + ").or_else(|| text.find("pc - ")), addr) {
+            (Some(i), Some(addr)) => {
+                let negative = &text[i + 3..i + 4] == "-";
+                let offset = text[i + 5..]
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .next()
+                    .and_then(parse_imm);
+                match offset {
+                    Some(offset) => {
+                        let offset = if negative { -offset } else { offset };
+                        let end = text[i + 5..].find(',').map_or(text.len(), |j| i + 5 + j);
+                        let target = addr.wrapping_add(offset as u64);
+                        (format!("{}{}", &text[..i], &text[end..]), Some((i, target)))
+                    }
+                    None => (text.to_string(), None),
+                }
+            }
+            _ => (text.to_string(), None),
+        };
+
+        // Leave the mnemonic alone, vector mnemonics contain things like `e64`.
+        let start = text.find(' ').unwrap_or(text.len());
+        let mut r = text[..start].to_string();
+        let operands = &text[start..];
+        let mut chars = operands.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if let Some((pos, target)) = target {
+                if start + i == pos {
+                    r.push_str(&self.target(target, symbols));
+                }
+            }
+            let negative = c == '-' && matches!(chars.peek(), Some((_, c)) if c.is_ascii_digit());
+            if !(c.is_ascii_alphanumeric() || c == '_' || negative) {
+                r.push(c);
+                continue;
+            }
+            let mut end = i + c.len_utf8();
+            while let Some((j, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || *c == '_') {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            r.push_str(&self.token(&operands[i..end]));
+        }
+        if let Some((pos, target)) = target {
+            if pos >= start + operands.len() {
+                r.push_str(&self.target(target, symbols));
+            }
+        }
+        r
+    }
+
+    fn token(&self, token: &str) -> String {
+        if !self.options.abi_names {
+            if let Some(n) = XREG_NAMES.iter().position(|&r| r == token) {
+                return format!("x{}", n);
+            }
+            if let Some(n) = FREG_NAMES.iter().position(|&r| r == token) {
+                return format!("f{}", n);
+            }
+        }
+        match (self.options.immediates, parse_imm(token)) {
+            (Immediates::Decimal, Some(imm)) => imm.to_string(),
+            (Immediates::Hex, Some(imm)) if imm < 0 => format!("-0x{:x}", -imm),
+            (Immediates::Hex, Some(imm)) => format!("0x{:x}", imm),
+            _ => token.to_string(),
+        }
+    }
+
+    /// An absolute target, labelled with the closest preceding symbol.
+    fn target(&self, target: u64, symbols: &BTreeMap<u64, String>) -> String {
+        match symbols.range(..=target).next_back() {
+            Some((addr, name)) if *addr == target => format!("0x{:x} <{}>", target, name),
+            Some((addr, name)) => format!("0x{:x} <{}+0x{:x}>", target, name, target - addr),
+            None => format!("0x{:x}", target),
+        }
+    }
+}
+
+fn parse_imm(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let imm = match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if s.bytes().all(|b| b.is_ascii_digit()) => s.parse().ok()?,
+        None => return None,
+    };
+    Some(if negative { -imm } else { imm })
+}
+
+impl Drop for Disassembler {
+    fn drop(&mut self) {
+        unsafe { rvv_delete_disassembler(self.addr) }
+    }
+}
+
+/// Named symbols by address. Functions win over other symbols at the same
+/// address; mapping symbols like `$x` are skipped.
+pub fn symbols(elf: &Elf) -> BTreeMap<u64, String> {
+    let mut r = BTreeMap::new();
+    let mut functions = BTreeMap::new();
+    for sym in elf.syms.iter() {
+        let name = match elf.strtab.get_at(sym.st_name) {
+            Some(name) if !name.is_empty() && !name.starts_with('$') => name,
+            _ => continue,
+        };
+        if sym.st_shndx == 0 || sym.is_import() {
+            continue;
+        }
+        if sym.is_function() {
+            functions
+                .entry(sym.st_value)
+                .or_insert_with(|| name.to_string());
+        } else {
+            r.entry(sym.st_value).or_insert_with(|| name.to_string());
+        }
+    }
+    r.extend(functions);
+    r
+}
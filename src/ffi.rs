@@ -0,0 +1,5 @@
+//! Raw bindings to cpp/spike-interfaces.h, generated by bindgen when building.
+//! Prefer [`Spike`](crate::Spike) and [`Disassembler`](crate::disasm::Disassembler).
+#![allow(non_camel_case_types, non_upper_case_globals, non_snake_case)]
+
+include!(concat!(env!("OUT_DIR"), "/ffi.rs"));
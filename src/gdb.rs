@@ -0,0 +1,556 @@
+//! A GDB remote server for a [`Spike`], built on `gdbstub`.
+//!
+//! The target description covers the x, f and v registers and the
+//! floating-point and vector CSRs, with v0-v31 sized by the processor's VLEN.
+//! Memory is read and written with [`Spike::load_mem`] and
+//! [`Spike::store_mem`]; breakpoints, watchpoints and single-stepping use
+//! [`Spike::run_for`] and friends. A program stored in memory can then be
+//! debugged with e.g.
+//!
+//! ```text
+//! riscv64-unknown-elf-gdb -ex 'target remote :9001' program.elf
+//! ```
+//!
+//! Traps stop with a signal, e.g. SIGILL for an illegal instruction and
+//! SIGTRAP for `ecall` and `ebreak`; the pc stays at the trapping instruction.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io;
+use std::marker::PhantomData;
+use std::net::{TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::Path;
+
+use gdbstub::arch::Arch;
+use gdbstub::common::Signal;
+use gdbstub::conn::{Connection, ConnectionExt};
+use gdbstub::stub::run_blocking::{self, BlockingEventLoop};
+use gdbstub::stub::{GdbStub, GdbStubError, SingleThreadStopReason};
+use gdbstub::target::ext::base::singlethread::{
+    SingleThreadBase, SingleThreadResume, SingleThreadResumeOps, SingleThreadSingleStep,
+    SingleThreadSingleStepOps,
+};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::{
+    Breakpoints, BreakpointsOps, HwWatchpoint, HwWatchpointOps, SwBreakpoint, SwBreakpointOps,
+    WatchKind,
+};
+use gdbstub::target::ext::target_description_xml_override::{
+    TargetDescriptionXmlOverride, TargetDescriptionXmlOverrideOps,
+};
+use gdbstub::target::{Target, TargetError, TargetResult};
+
+pub use gdbstub::stub::DisconnectReason;
+
+use crate::insn::FV_CSRS;
+use crate::run::{Access, StopReason};
+use crate::{Error, Spike};
+
+/// Instructions run between checks for an interrupt from GDB.
+const CHUNK: u64 = 0x10000;
+
+#[derive(Debug)]
+pub enum ServeError {
+    Io(io::Error),
+    Gdb(GdbStubError<Error, io::Error>),
+}
+
+impl std::fmt::Display for ServeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServeError::Io(e) => write!(f, "{}", e),
+            ServeError::Gdb(e) => write!(f, "gdbstub: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ServeError {}
+
+impl From<io::Error> for ServeError {
+    fn from(e: io::Error) -> Self {
+        ServeError::Io(e)
+    }
+}
+
+/// Serve `spike` to a GDB already connected over `conn`, until GDB detaches
+/// or kills the session.
+pub fn serve<C>(spike: &Spike, conn: C) -> Result<DisconnectReason, ServeError>
+where
+    C: ConnectionExt<Error = io::Error> + 'static,
+{
+    let mut target = SpikeTarget::new(spike);
+    let conn: Box<dyn ConnectionExt<Error = io::Error>> = Box::new(conn);
+    GdbStub::new(conn)
+        .run_blocking::<EventLoop>(&mut target)
+        .map_err(ServeError::Gdb)
+}
+
+/// Wait for one GDB connection on `addr`, e.g. `"127.0.0.1:9001"`, and
+/// [`serve`] it.
+pub fn serve_tcp<A: ToSocketAddrs>(spike: &Spike, addr: A) -> Result<DisconnectReason, ServeError> {
+    let listener = TcpListener::bind(addr)?;
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(true)?;
+    serve(spike, stream)
+}
+
+/// Like [`serve_tcp`], listening on a Unix socket at `path`. A file left at
+/// `path` by an earlier server is replaced.
+#[cfg(unix)]
+pub fn serve_unix<P: AsRef<Path>>(spike: &Spike, path: P) -> Result<DisconnectReason, ServeError> {
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let listener = UnixListener::bind(&path)?;
+    let (stream, _) = listener.accept()?;
+    serve(spike, stream)
+}
+
+enum Riscv64Rvv {}
+
+impl Arch for Riscv64Rvv {
+    type Usize = u64;
+    type Registers = Registers;
+    type BreakpointKind = usize;
+    type RegId = ();
+}
+
+/// The registers in target description order: x0-x31, pc, f0-f31, [`FV_CSRS`]
+/// and v0-v31.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Registers {
+    x: [u64; 32],
+    pc: u64,
+    f: [u64; 32],
+    /// `None` if the CSR couldn't be read.
+    csrs: [Option<u64>; FV_CSRS.len()],
+    /// VLEN/8 bytes each.
+    v: Vec<Vec<u8>>,
+}
+
+impl gdbstub::arch::Registers for Registers {
+    type ProgramCounter = u64;
+
+    fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    fn gdb_serialize(&self, mut write_byte: impl FnMut(Option<u8>)) {
+        let mut write_u64 = |x: Option<u64>| match x {
+            Some(x) => x.to_le_bytes().iter().for_each(|b| write_byte(Some(*b))),
+            None => (0..8).for_each(|_| write_byte(None)),
+        };
+        self.x.iter().for_each(|x| write_u64(Some(*x)));
+        write_u64(Some(self.pc));
+        self.f.iter().for_each(|f| write_u64(Some(*f)));
+        self.csrs.iter().for_each(|csr| write_u64(*csr));
+        for v in &self.v {
+            v.iter().for_each(|b| write_byte(Some(*b)));
+        }
+    }
+
+    fn gdb_deserialize(&mut self, bytes: &[u8]) -> Result<(), ()> {
+        let fixed = 8 * (32 + 1 + 32 + FV_CSRS.len());
+        let vlenb = bytes.len().saturating_sub(fixed) / 32;
+        if bytes.len() != fixed + 32 * vlenb {
+            return Err(());
+        }
+        let (words, vregs) = bytes.split_at(fixed);
+        let mut words = words
+            .chunks(8)
+            .map(|w| u64::from_le_bytes([w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7]]));
+        for x in self.x.iter_mut() {
+            *x = words.next().unwrap();
+        }
+        self.pc = words.next().unwrap();
+        for f in self.f.iter_mut() {
+            *f = words.next().unwrap();
+        }
+        for csr in self.csrs.iter_mut() {
+            *csr = words.next();
+        }
+        self.v = if vlenb == 0 {
+            vec![]
+        } else {
+            vregs.chunks(vlenb).map(|v| v.to_vec()).collect()
+        };
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExecMode {
+    Continue,
+    Step,
+}
+
+struct SpikeTarget<'a> {
+    spike: &'a Spike,
+    mode: ExecMode,
+    /// Those added by GDB, see [`EventLoop`].
+    breakpoints: BTreeSet<u64>,
+    xml: String,
+}
+
+impl<'a> SpikeTarget<'a> {
+    fn new(spike: &'a Spike) -> Self {
+        Self {
+            spike,
+            mode: ExecMode::Continue,
+            breakpoints: BTreeSet::new(),
+            xml: target_xml(spike.get_vlen() / 8),
+        }
+    }
+
+    /// Run in the current mode until something GDB should hear about stops
+    /// execution. Returns `None` if the instruction limit was reached.
+    fn run_chunk(&self) -> Result<Option<SingleThreadStopReason<u64>>, Error> {
+        let stop = match self.mode {
+            ExecMode::Step => self.spike.step(),
+            ExecMode::Continue => self.spike.run_for(CHUNK),
+        };
+        let reason = match stop {
+            StopReason::Breakpoint { .. } => SingleThreadStopReason::SwBreak(()),
+            StopReason::Watchpoint { addr, access, .. } => SingleThreadStopReason::Watch {
+                tid: (),
+                kind: if access == Access::Read {
+                    WatchKind::Read
+                } else {
+                    WatchKind::Write
+                },
+                addr,
+            },
+            StopReason::Condition { .. } => SingleThreadStopReason::Signal(Signal::SIGTRAP),
+            StopReason::Trap { error, .. } => match error.trap_cause() {
+                Some(cause) => SingleThreadStopReason::Signal(trap_signal(cause)),
+                None => return Err(error),
+            },
+            StopReason::Limit { .. } if self.mode == ExecMode::Step => {
+                SingleThreadStopReason::DoneStep
+            }
+            StopReason::Limit { .. } => return Ok(None),
+        };
+        Ok(Some(reason))
+    }
+
+    fn read(&self) -> Result<Registers, Error> {
+        let mut regs = Registers::default();
+        for (i, x) in regs.x.iter_mut().enumerate() {
+            *x = self.spike.get_xreg(i as u64)?;
+        }
+        regs.pc = self.spike.pc();
+        for (i, f) in regs.f.iter_mut().enumerate() {
+            *f = self.spike.get_freg(i as u64)?;
+        }
+        for ((_, which), csr) in FV_CSRS.iter().zip(regs.csrs.iter_mut()) {
+            *csr = self.spike.get_csr(*which as u64).ok();
+        }
+        let vlenb = self.spike.get_vlen() as usize / 8;
+        let mut file = vec![0; 32 * vlenb];
+        self.spike.get_vreg(0, &mut file)?;
+        regs.v = file.chunks(vlenb).map(|v| v.to_vec()).collect();
+        Ok(regs)
+    }
+}
+
+/// The signal GDB is told about for a trap with `cause`.
+fn trap_signal(cause: u64) -> Signal {
+    match cause {
+        // misaligned fetch, load and store
+        0 | 4 | 6 => Signal::SIGBUS,
+        2 => Signal::SIGILL,
+        // breakpoint and environment calls
+        3 | 8..=11 => Signal::SIGTRAP,
+        _ => Signal::SIGSEGV,
+    }
+}
+
+/// The target description for a processor with `vlenb` bytes per vector
+/// register, laid out like QEMU's.
+fn target_xml(vlenb: u64) -> String {
+    let mut xml = String::new();
+    xml.push_str(concat!(
+        r#"<?xml version="1.0"?>"#,
+        r#"<!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+        r#"<target version="1.0">"#,
+        "<architecture>riscv:rv64</architecture>",
+        r#"<feature name="org.gnu.gdb.riscv.cpu">"#,
+    ));
+    for i in 0..32 {
+        write!(xml, r#"<reg name="x{}" bitsize="64" type="int"/>"#, i).unwrap();
+    }
+    xml.push_str(r#"<reg name="pc" bitsize="64" type="code_ptr"/></feature>"#);
+
+    xml.push_str(r#"<feature name="org.gnu.gdb.riscv.fpu">"#);
+    for i in 0..32 {
+        write!(
+            xml,
+            r#"<reg name="f{}" bitsize="64" type="ieee_double"/>"#,
+            i
+        )
+        .unwrap();
+    }
+    xml.push_str("</feature>");
+
+    xml.push_str(r#"<feature name="org.gnu.gdb.riscv.csr">"#);
+    for (name, _) in FV_CSRS.iter() {
+        write!(xml, r#"<reg name="{}" bitsize="64" type="int"/>"#, name).unwrap();
+    }
+    xml.push_str("</feature>");
+
+    xml.push_str(r#"<feature name="org.gnu.gdb.riscv.vector">"#);
+    let fields = [("b", 8), ("s", 16), ("w", 32), ("l", 64), ("q", 128)];
+    for (name, bits) in fields.iter() {
+        let count = vlenb * 8 / bits;
+        if count > 0 {
+            write!(
+                xml,
+                r#"<vector id="{}" type="uint{}" count="{}"/>"#,
+                name, bits, count
+            )
+            .unwrap();
+        }
+    }
+    xml.push_str(r#"<union id="riscv_vector">"#);
+    for (name, bits) in fields.iter() {
+        if vlenb * 8 / bits > 0 {
+            write!(xml, r#"<field name="{}" type="{}"/>"#, name, name).unwrap();
+        }
+    }
+    xml.push_str("</union>");
+    for i in 0..32 {
+        write!(
+            xml,
+            r#"<reg name="v{}" bitsize="{}" type="riscv_vector"/>"#,
+            i,
+            vlenb * 8
+        )
+        .unwrap();
+    }
+    xml.push_str("</feature></target>");
+    xml
+}
+
+impl Target for SpikeTarget<'_> {
+    type Arch = Riscv64Rvv;
+    type Error = Error;
+
+    fn base_ops(&mut self) -> BaseOps<'_, Riscv64Rvv, Error> {
+        BaseOps::SingleThread(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_target_description_xml_override(
+        &mut self,
+    ) -> Option<TargetDescriptionXmlOverrideOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadBase for SpikeTarget<'_> {
+    fn read_registers(&mut self, regs: &mut Registers) -> TargetResult<(), Self> {
+        *regs = self.read().map_err(TargetError::Fatal)?;
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &Registers) -> TargetResult<(), Self> {
+        let old = self.read().map_err(TargetError::Fatal)?;
+        for (i, x) in regs.x.iter().enumerate().skip(1) {
+            self.spike
+                .set_xreg(i as u64, *x)
+                .map_err(TargetError::Fatal)?;
+        }
+        self.spike.set_pc(regs.pc);
+        for (i, f) in regs.f.iter().enumerate() {
+            if *f != old.f[i] {
+                self.spike
+                    .set_freg(i as u64, *f)
+                    .map_err(TargetError::Fatal)?;
+            }
+        }
+        // Only changed CSRs are written, so that writing vxrm isn't undone by
+        // writing back the old vcsr, and read-only ones like vl can be left
+        // alone.
+        for (((_, which), csr), old) in FV_CSRS.iter().zip(&regs.csrs).zip(&old.csrs) {
+            if let Some(csr) = csr {
+                if Some(*csr) != *old {
+                    self.spike
+                        .set_csr(*which as u64, *csr)
+                        .map_err(|_| TargetError::NonFatal)?;
+                }
+            }
+        }
+        if regs.v != old.v {
+            let file = regs.v.concat();
+            if file.len() != old.v.concat().len() {
+                return Err(TargetError::NonFatal);
+            }
+            self.spike.set_vreg(0, &file).map_err(TargetError::Fatal)?;
+        }
+        Ok(())
+    }
+
+    fn read_addrs(&mut self, start_addr: u64, data: &mut [u8]) -> TargetResult<usize, Self> {
+        self.spike
+            .load_mem(start_addr, data)
+            .map_err(|_| TargetError::NonFatal)?;
+        Ok(data.len())
+    }
+
+    fn write_addrs(&mut self, start_addr: u64, data: &[u8]) -> TargetResult<(), Self> {
+        self.spike
+            .store_mem(start_addr, data)
+            .map_err(|_| TargetError::NonFatal)
+    }
+
+    fn support_resume(&mut self) -> Option<SingleThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+// Signals GDB asks to resume with are ignored, traps aren't delivered to the
+// program.
+impl SingleThreadResume for SpikeTarget<'_> {
+    fn resume(&mut self, _signal: Option<Signal>) -> Result<(), Error> {
+        self.mode = ExecMode::Continue;
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<SingleThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadSingleStep for SpikeTarget<'_> {
+    fn step(&mut self, _signal: Option<Signal>) -> Result<(), Error> {
+        self.mode = ExecMode::Step;
+        Ok(())
+    }
+}
+
+impl Breakpoints for SpikeTarget<'_> {
+    fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for SpikeTarget<'_> {
+    fn add_sw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        self.spike.add_breakpoint(addr);
+        self.breakpoints.insert(addr);
+        Ok(true)
+    }
+
+    fn remove_sw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        self.breakpoints.remove(&addr);
+        Ok(self.spike.remove_breakpoint(addr))
+    }
+}
+
+fn access(kind: WatchKind) -> Access {
+    match kind {
+        WatchKind::Read => Access::Read,
+        WatchKind::Write => Access::Write,
+        WatchKind::ReadWrite => Access::ReadWrite,
+    }
+}
+
+impl HwWatchpoint for SpikeTarget<'_> {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let end = addr.checked_add(len).ok_or(TargetError::NonFatal)?;
+        // Fails without memory, which GDB is told as "not supported".
+        Ok(self.spike.add_watchpoint(addr..end, access(kind)).is_ok())
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let end = addr.checked_add(len).ok_or(TargetError::NonFatal)?;
+        Ok(self.spike.remove_watchpoint(addr..end, access(kind)))
+    }
+}
+
+impl TargetDescriptionXmlOverride for SpikeTarget<'_> {
+    fn target_description_xml(
+        &self,
+        annex: &[u8],
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        if annex != b"target.xml" {
+            return Err(TargetError::NonFatal);
+        }
+        let xml = self.xml.as_bytes();
+        let start = (offset as usize).min(xml.len());
+        let end = start
+            .saturating_add(length)
+            .min(xml.len())
+            .min(start.saturating_add(buf.len()));
+        buf[..end - start].copy_from_slice(&xml[start..end]);
+        Ok(end - start)
+    }
+}
+
+struct EventLoop<'a>(PhantomData<&'a ()>);
+
+impl<'a> BlockingEventLoop for EventLoop<'a> {
+    type Target = SpikeTarget<'a>;
+    type Connection = Box<dyn ConnectionExt<Error = io::Error>>;
+    type StopReason = SingleThreadStopReason<u64>;
+
+    #[allow(clippy::type_complexity)]
+    fn wait_for_stop_reason(
+        target: &mut SpikeTarget<'a>,
+        conn: &mut Self::Connection,
+    ) -> Result<
+        run_blocking::Event<SingleThreadStopReason<u64>>,
+        run_blocking::WaitForStopReasonError<Error, <Self::Connection as Connection>::Error>,
+    > {
+        loop {
+            if let Some(reason) = target
+                .run_chunk()
+                .map_err(run_blocking::WaitForStopReasonError::Target)?
+            {
+                return Ok(run_blocking::Event::TargetStopped(reason));
+            }
+            // A chunk ending at a breakpoint would skip it when the next one
+            // starts there.
+            if target.breakpoints.contains(&target.spike.pc()) {
+                return Ok(run_blocking::Event::TargetStopped(
+                    SingleThreadStopReason::SwBreak(()),
+                ));
+            }
+            if conn.peek().map(|b| b.is_some()).unwrap_or(true) {
+                let byte = conn
+                    .read()
+                    .map_err(run_blocking::WaitForStopReasonError::Connection)?;
+                return Ok(run_blocking::Event::IncomingData(byte));
+            }
+        }
+    }
+
+    fn on_interrupt(
+        _target: &mut SpikeTarget<'a>,
+    ) -> Result<Option<SingleThreadStopReason<u64>>, Error> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
@@ -0,0 +1,208 @@
+//! Random RVV instruction generation.
+//!
+//! Instructions are drawn from [`opcodes::OPCODES`] and their operands are
+//! picked so that the encoding is legal under the configured vtype: register
+//! groups are aligned to EMUL, masked instructions don't write v0 and
+//! destinations don't overlap sources where the specification forbids it.
+//! Set `allow_reserved` to fill every variable field with random bits instead.
+pub use rand_core::RngCore;
+
+use rand_core::impls;
+
+use crate::opcodes::{self, Emul, InsnClass, Opcode, Operand, Vm};
+use crate::vtype::VType;
+
+pub struct Generator {
+    /// The vtype in effect, or `None` if vill is set.
+    pub vtype: Option<VType>,
+    pub elen: u32,
+    pub allow_reserved: bool,
+    /// x register used as the base address of loads and stores.
+    pub base_reg: u32,
+    /// x register used as the stride of strided loads and stores.
+    pub stride_reg: u32,
+}
+
+impl Generator {
+    pub fn new(vtype: Option<VType>, elen: u32) -> Self {
+        Self {
+            vtype,
+            elen,
+            allow_reserved: false,
+            base_reg: 1,
+            stride_reg: 2,
+        }
+    }
+
+    /// All opcodes of `class` that can be encoded legally under the current vtype.
+    pub fn candidates(&self, class: InsnClass) -> Vec<&'static Opcode> {
+        opcodes::OPCODES
+            .iter()
+            .filter(|o| o.class == class)
+            .filter(|o| self.allow_reserved || self.is_legal(o))
+            .collect()
+    }
+
+    /// Generate a random instruction of `class`. Returns `None` if no
+    /// instruction of this class is legal under the current vtype, e.g.
+    /// widening when SEW == ELEN.
+    pub fn generate<R: RngCore>(&self, rng: &mut R, class: InsnClass) -> Option<u32> {
+        let candidates = self.candidates(class);
+        if candidates.is_empty() {
+            return None;
+        }
+        let opcode = candidates[rng.next_u32() as usize % candidates.len()];
+        self.generate_opcode(rng, opcode)
+    }
+
+    /// Generate a random instance of a specific opcode.
+    pub fn generate_opcode<R: RngCore>(&self, rng: &mut R, opcode: &Opcode) -> Option<u32> {
+        if self.allow_reserved {
+            let mut insn = rng.next_u32() & !opcode.mask | opcode.bits;
+            // Address registers stay fixed, harnesses set up memory around them.
+            if opcode.vs1 == Operand::Base {
+                insn = insn & !(0b11111 << 15) | self.base_reg << 15;
+            }
+            if opcode.vs2 == Operand::Stride {
+                insn = insn & !(0b11111 << 20) | self.stride_reg << 20;
+            }
+            return Some(insn);
+        }
+        if !self.is_legal(opcode) {
+            return None;
+        }
+        let vm = match opcode.vm {
+            Vm::Free => rng.next_u32() & 1,
+            Vm::Masked => 0,
+            Vm::Unmasked => 1,
+        };
+        let vs2 = self.pick_source(rng, opcode.vs2);
+        let vs1 = self.pick_source(rng, opcode.vs1);
+        let vd = match opcode.vd {
+            Operand::V(emul) => {
+                let n = self.regs(emul)?;
+                let mut slots: Vec<u32> = (0..32).step_by(n as usize).collect();
+                if vm == 0 && !opcode.mask_dest {
+                    slots.retain(|&r| r != 0);
+                }
+                if opcode.disjoint {
+                    for (field, operand) in [(vs2, opcode.vs2), (vs1, opcode.vs1)].iter() {
+                        if let Operand::V(e) = operand {
+                            let m = self.regs(*e)?;
+                            slots.retain(|&r| r + n <= *field || *field + m <= r);
+                        }
+                    }
+                }
+                if slots.is_empty() {
+                    return None;
+                }
+                slots[rng.next_u32() as usize % slots.len()]
+            }
+            operand => self.pick_source(rng, operand),
+        };
+        Some(opcode.bits | vm << 25 | vs2 << 20 | vs1 << 15 | vd << 7)
+    }
+
+    /// Whether `opcode` has a legal encoding under the current vtype.
+    pub fn is_legal(&self, opcode: &Opcode) -> bool {
+        let vtype = match self.vtype {
+            Some(vtype) => vtype,
+            None => return false,
+        };
+        if !vtype.is_legal(self.elen) {
+            return false;
+        }
+        let widens = opcode.class == InsnClass::Widening || opcode.class == InsnClass::Narrowing;
+        if widens && vtype.sew * 2 > self.elen {
+            return false;
+        }
+        [opcode.vd, opcode.vs2, opcode.vs1].iter().all(|o| match o {
+            Operand::V(emul) => self.regs(*emul).is_some(),
+            _ => true,
+        })
+    }
+
+    /// The number of registers occupied by an operand, or `None` if its EEW or
+    /// EMUL isn't supported.
+    fn regs(&self, emul: Emul) -> Option<u32> {
+        let vtype = self.vtype?;
+        match emul {
+            Emul::One => Some(1),
+            Emul::Regs(n) => Some(n as u32),
+            Emul::Lmul => Some(vtype.lmul.regs()),
+            Emul::Wide => {
+                if vtype.sew * 2 > self.elen {
+                    return None;
+                }
+                vtype.emul_regs(vtype.sew * 2)
+            }
+            Emul::Frac(n) => {
+                let eew = vtype.sew / n as u32;
+                if eew < 8 {
+                    return None;
+                }
+                vtype.emul_regs(eew)
+            }
+            Emul::Eew(eew) => {
+                if eew as u32 > self.elen {
+                    return None;
+                }
+                vtype.emul_regs(eew as u32)
+            }
+        }
+    }
+
+    fn pick_source<R: RngCore>(&self, rng: &mut R, operand: Operand) -> u32 {
+        match operand {
+            // Fixed fields are already part of `bits`.
+            Operand::Fixed(_) => 0,
+            Operand::X | Operand::Simm5 | Operand::Uimm5 => rng.next_u32() & 0b11111,
+            Operand::Base => self.base_reg,
+            Operand::Stride => self.stride_reg,
+            Operand::V(emul) => {
+                let n = self.regs(emul).unwrap_or(1);
+                rng.next_u32() % (32 / n) * n
+            }
+        }
+    }
+}
+
+/// An [`RngCore`] reading from a byte slice, so fuzz inputs can drive the
+/// generators directly. Yields zeros once the input is exhausted.
+pub struct ByteRng<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteRng<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<'a> RngCore for ByteRng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let n = std::cmp::min(dest.len(), self.data.len());
+        dest[..n].copy_from_slice(&self.data[..n]);
+        for b in dest[n..].iter_mut() {
+            *b = 0;
+        }
+        self.data = &self.data[n..];
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
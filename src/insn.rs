@@ -0,0 +1,680 @@
+//! A typed builder for RVV encodings.
+//!
+//! Every constructor is named after its mnemonic with `.` replaced by `_` and
+//! takes the operands in assembly order, so `vadd.vv v2, v10, v20` is
+//! `VInsn::vadd_vv(V2, V10, V20)` and `vmacc.vv vd, vs1, vs2` is
+//! `VInsn::vmacc_vv(vd, vs1, vs2)`. Instructions are unmasked unless
+//! [`VInsn::masked`] is called. Registers are enums and immediates are `i8`
+//! (simm5) or `u8` (uimm5), constructors panic if an immediate doesn't fit.
+use crate::vtype::{VSet, VSetForm, VType};
+
+const OP_V: u32 = 0b1010111;
+const LOAD_FP: u32 = 0b0000111;
+const STORE_FP: u32 = 0b0100111;
+
+pub const OPIVV: u32 = 0b000;
+pub const OPFVV: u32 = 0b001;
+pub const OPMVV: u32 = 0b010;
+pub const OPIVI: u32 = 0b011;
+pub const OPIVX: u32 = 0b100;
+pub const OPFVF: u32 = 0b101;
+pub const OPMVX: u32 = 0b110;
+
+/// ABI names of the x registers, as printed by Spike's disassembler.
+#[rustfmt::skip]
+pub const XREG_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+/// ABI names of the f registers.
+#[rustfmt::skip]
+pub const FREG_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7",
+    "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5",
+    "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+    "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// The CSRs of the F and V extensions, by name.
+pub const FV_CSRS: [(&str, u32); 10] = [
+    ("fflags", 0x001),
+    ("frm", 0x002),
+    ("fcsr", 0x003),
+    ("vstart", 0x008),
+    ("vxsat", 0x009),
+    ("vxrm", 0x00a),
+    ("vcsr", 0x00f),
+    ("vl", 0xc20),
+    ("vtype", 0xc21),
+    ("vlenb", 0xc22),
+];
+
+/// The unprivileged counters, by name.
+const COUNTER_CSRS: [(&str, u32); 3] = [("cycle", 0xc00), ("time", 0xc01), ("instret", 0xc02)];
+
+/// Every CSR known by name, as the assembler accepts them.
+pub fn csrs() -> impl Iterator<Item = &'static (&'static str, u32)> {
+    FV_CSRS.iter().chain(COUNTER_CSRS.iter())
+}
+
+macro_rules! registers {
+    ($name:ident, [$($variant:ident),*], |$r:ident, $f:ident| $fmt:expr) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum $name {
+            $($variant),*
+        }
+
+        impl $name {
+            pub const ALL: [$name; 32] = [$($name::$variant),*];
+
+            pub fn new(index: u32) -> Option<$name> {
+                Self::ALL.get(index as usize).copied()
+            }
+
+            pub fn index(self) -> u32 {
+                self as u32
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, $f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let $r = *self;
+                $fmt
+            }
+        }
+    };
+}
+
+registers!(
+    VReg,
+    [
+        V0, V1, V2, V3, V4, V5, V6, V7, V8, V9, V10, V11, V12, V13, V14, V15, V16, V17, V18, V19,
+        V20, V21, V22, V23, V24, V25, V26, V27, V28, V29, V30, V31
+    ],
+    |r, f| write!(f, "v{}", r.index())
+);
+
+registers!(
+    XReg,
+    [
+        X0, X1, X2, X3, X4, X5, X6, X7, X8, X9, X10, X11, X12, X13, X14, X15, X16, X17, X18, X19,
+        X20, X21, X22, X23, X24, X25, X26, X27, X28, X29, X30, X31
+    ],
+    |r, f| f.write_str(XREG_NAMES[r.index() as usize])
+);
+
+registers!(
+    FReg,
+    [
+        F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19,
+        F20, F21, F22, F23, F24, F25, F26, F27, F28, F29, F30, F31
+    ],
+    |r, f| f.write_str(FREG_NAMES[r.index() as usize])
+);
+
+impl XReg {
+    pub const ZERO: XReg = XReg::X0;
+    pub const RA: XReg = XReg::X1;
+    pub const SP: XReg = XReg::X2;
+    pub const GP: XReg = XReg::X3;
+    pub const TP: XReg = XReg::X4;
+    pub const T0: XReg = XReg::X5;
+    pub const T1: XReg = XReg::X6;
+    pub const T2: XReg = XReg::X7;
+    pub const S0: XReg = XReg::X8;
+    pub const S1: XReg = XReg::X9;
+    pub const A0: XReg = XReg::X10;
+    pub const A1: XReg = XReg::X11;
+    pub const A2: XReg = XReg::X12;
+    pub const A3: XReg = XReg::X13;
+    pub const A4: XReg = XReg::X14;
+    pub const A5: XReg = XReg::X15;
+    pub const A6: XReg = XReg::X16;
+    pub const A7: XReg = XReg::X17;
+    pub const T3: XReg = XReg::X28;
+    pub const T4: XReg = XReg::X29;
+    pub const T5: XReg = XReg::X30;
+    pub const T6: XReg = XReg::X31;
+}
+
+/// The mask operand. RVV only masks with v0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mask {
+    V0,
+}
+
+/// Element width of a load or store, including the `mew` bit for EEW > 64.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eew {
+    E8,
+    E16,
+    E32,
+    E64,
+    E128,
+    E256,
+    E512,
+    E1024,
+}
+
+impl Eew {
+    pub const ALL: [Eew; 8] = [
+        Eew::E8,
+        Eew::E16,
+        Eew::E32,
+        Eew::E64,
+        Eew::E128,
+        Eew::E256,
+        Eew::E512,
+        Eew::E1024,
+    ];
+
+    pub fn bits(self) -> u32 {
+        8 << self as u32
+    }
+
+    /// `mew` (bit 28) and `width` (bits 14:12).
+    fn fields(self) -> u32 {
+        let width = [0b000, 0b101, 0b110, 0b111][self as usize % 4];
+        (self as u32 / 4) << 28 | width << 12
+    }
+}
+
+/// Number of fields of a segment load or store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nf {
+    N1,
+    N2,
+    N3,
+    N4,
+    N5,
+    N6,
+    N7,
+    N8,
+}
+
+/// Number of registers of a whole register load, store or move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nreg {
+    R1 = 0,
+    R2 = 1,
+    R4 = 3,
+    R8 = 7,
+}
+
+/// Something that can be placed in a 5-bit register or immediate field.
+pub trait Field: Copy {
+    fn field(self) -> u32;
+}
+
+impl Field for VReg {
+    fn field(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Field for XReg {
+    fn field(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Field for FReg {
+    fn field(self) -> u32 {
+        self as u32
+    }
+}
+
+/// simm5.
+impl Field for i8 {
+    fn field(self) -> u32 {
+        assert!((-16..16).contains(&self), "simm5 out of range: {}", self);
+        self as u32 & 0b11111
+    }
+}
+
+/// uimm5.
+impl Field for u8 {
+    fn field(self) -> u32 {
+        assert!(self < 32, "uimm5 out of range: {}", self);
+        self as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VInsn {
+    bits: u32,
+    maskable: bool,
+    segment: bool,
+}
+
+impl VInsn {
+    fn new(bits: u32, maskable: bool) -> Self {
+        Self {
+            bits,
+            maskable,
+            segment: false,
+        }
+    }
+
+    fn op(funct6: u32, funct3: u32, vm: bool, vd: u32, vs2: u32, vs1: u32) -> Self {
+        Self::new(
+            funct6 << 26
+                | (vm as u32) << 25
+                | vs2 << 20
+                | vs1 << 15
+                | funct3 << 12
+                | vd << 7
+                | OP_V,
+            vm,
+        )
+    }
+
+    fn mem(opcode: u32, mop: u32, eew: Eew, vd: VReg, rs1: XReg, rs2: u32) -> Self {
+        Self {
+            bits: mop << 26
+                | 1 << 25
+                | rs2 << 20
+                | rs1.field() << 15
+                | eew.fields()
+                | vd.field() << 7
+                | opcode,
+            maskable: true,
+            segment: true,
+        }
+    }
+
+    /// Execute under the mask in v0.
+    ///
+    /// # Panics
+    ///
+    /// If the instruction has no masked form, e.g. `vmv.v.v` or the mask logical
+    /// instructions. Carry and merge instructions take their v0 operand in the
+    /// constructor instead.
+    pub fn masked(self, _mask: Mask) -> Self {
+        assert!(
+            self.maskable,
+            "instruction 0x{:08x} can't be masked",
+            self.bits
+        );
+        Self {
+            bits: self.bits & !(1 << 25),
+            ..self
+        }
+    }
+
+    /// Turn a unit-stride, strided or indexed load/store into its segment form.
+    ///
+    /// # Panics
+    ///
+    /// If the instruction isn't one of those.
+    pub fn segment(self, nf: Nf) -> Self {
+        assert!(
+            self.segment,
+            "instruction 0x{:08x} has no segment form",
+            self.bits
+        );
+        Self {
+            bits: self.bits & !(0b111 << 29) | (nf as u32) << 29,
+            ..self
+        }
+    }
+
+    pub fn encode(self) -> u32 {
+        self.bits
+    }
+
+    pub fn vsetvli(rd: XReg, rs1: XReg, vtype: VType) -> Self {
+        Self::vset(VSetForm::Vsetvli, rd, rs1, XReg::X0, 0, vtype)
+    }
+
+    pub fn vsetivli(rd: XReg, uimm: u8, vtype: VType) -> Self {
+        assert!(uimm < 32, "uimm5 out of range: {}", uimm);
+        Self::vset(VSetForm::Vsetivli, rd, XReg::X0, XReg::X0, uimm, vtype)
+    }
+
+    pub fn vsetvl(rd: XReg, rs1: XReg, rs2: XReg) -> Self {
+        let vtype = VType::from_bits(0).unwrap();
+        Self::vset(VSetForm::Vsetvl, rd, rs1, rs2, 0, vtype)
+    }
+
+    fn vset(form: VSetForm, rd: XReg, rs1: XReg, rs2: XReg, avl: u8, vtype: VType) -> Self {
+        let vset = VSet {
+            form,
+            vtype: vtype.bits(),
+            avl: avl as u64,
+            rd: rd.field(),
+            rs1: rs1.field(),
+            rs2: rs2.field(),
+        };
+        Self::new(vset.encode(), false)
+    }
+
+    pub fn vle_v(eew: Eew, vd: VReg, rs1: XReg) -> Self {
+        Self::mem(LOAD_FP, 0b00, eew, vd, rs1, 0b00000)
+    }
+
+    pub fn vleff_v(eew: Eew, vd: VReg, rs1: XReg) -> Self {
+        Self::mem(LOAD_FP, 0b00, eew, vd, rs1, 0b10000)
+    }
+
+    pub fn vse_v(eew: Eew, vs3: VReg, rs1: XReg) -> Self {
+        Self::mem(STORE_FP, 0b00, eew, vs3, rs1, 0b00000)
+    }
+
+    pub fn vlse_v(eew: Eew, vd: VReg, rs1: XReg, rs2: XReg) -> Self {
+        Self::mem(LOAD_FP, 0b10, eew, vd, rs1, rs2.field())
+    }
+
+    pub fn vsse_v(eew: Eew, vs3: VReg, rs1: XReg, rs2: XReg) -> Self {
+        Self::mem(STORE_FP, 0b10, eew, vs3, rs1, rs2.field())
+    }
+
+    pub fn vluxei_v(eew: Eew, vd: VReg, rs1: XReg, vs2: VReg) -> Self {
+        Self::mem(LOAD_FP, 0b01, eew, vd, rs1, vs2.field())
+    }
+
+    pub fn vloxei_v(eew: Eew, vd: VReg, rs1: XReg, vs2: VReg) -> Self {
+        Self::mem(LOAD_FP, 0b11, eew, vd, rs1, vs2.field())
+    }
+
+    pub fn vsuxei_v(eew: Eew, vs3: VReg, rs1: XReg, vs2: VReg) -> Self {
+        Self::mem(STORE_FP, 0b01, eew, vs3, rs1, vs2.field())
+    }
+
+    pub fn vsoxei_v(eew: Eew, vs3: VReg, rs1: XReg, vs2: VReg) -> Self {
+        Self::mem(STORE_FP, 0b11, eew, vs3, rs1, vs2.field())
+    }
+
+    pub fn vlm_v(vd: VReg, rs1: XReg) -> Self {
+        Self::new(Self::vle_v(Eew::E8, vd, rs1).bits | 0b01011 << 20, false)
+    }
+
+    pub fn vsm_v(vs3: VReg, rs1: XReg) -> Self {
+        Self::new(Self::vse_v(Eew::E8, vs3, rs1).bits | 0b01011 << 20, false)
+    }
+
+    /// `vl<nreg>re<eew>.v`
+    pub fn vlre_v(nreg: Nreg, eew: Eew, vd: VReg, rs1: XReg) -> Self {
+        let bits = Self::vle_v(eew, vd, rs1).bits | (nreg as u32) << 29 | 0b01000 << 20;
+        Self::new(bits, false)
+    }
+
+    /// `vs<nreg>r.v`
+    pub fn vsr_v(nreg: Nreg, vs3: VReg, rs1: XReg) -> Self {
+        let bits = Self::vse_v(Eew::E8, vs3, rs1).bits | (nreg as u32) << 29 | 0b01000 << 20;
+        Self::new(bits, false)
+    }
+
+    /// `vmv<nreg>r.v`
+    pub fn vmvr_v(nreg: Nreg, vd: VReg, vs2: VReg) -> Self {
+        Self::op(0b100111, OPIVI, true, vd.field(), vs2.field(), nreg as u32).unmaskable()
+    }
+
+    fn unmaskable(self) -> Self {
+        Self {
+            maskable: false,
+            ..self
+        }
+    }
+}
+
+impl From<VInsn> for u32 {
+    fn from(insn: VInsn) -> u32 {
+        insn.bits
+    }
+}
+
+/// `op vd, vs2, vs1/rs1/imm`
+macro_rules! binary {
+    ($funct3:expr, $vs2:ty, $vs1:ty; $($name:ident = $funct6:expr),* $(,)?) => {
+        impl VInsn {
+            $(
+                pub fn $name(vd: VReg, vs2: $vs2, vs1: $vs1) -> Self {
+                    Self::op($funct6, $funct3, true, vd.field(), vs2.field(), vs1.field())
+                }
+            )*
+        }
+    };
+}
+
+/// Multiply-add, `op vd, vs1/rs1, vs2`.
+macro_rules! ternary {
+    ($funct3:expr, $vs1:ty; $($name:ident = $funct6:expr),* $(,)?) => {
+        impl VInsn {
+            $(
+                pub fn $name(vd: VReg, vs1: $vs1, vs2: VReg) -> Self {
+                    Self::op($funct6, $funct3, true, vd.field(), vs2.field(), vs1.field())
+                }
+            )*
+        }
+    };
+}
+
+/// Carry and merge, `op vd, vs2, vs1/rs1/imm, v0` with vm = 0.
+macro_rules! carry {
+    ($funct3:expr, $vs1:ty; $($name:ident = $funct6:expr),* $(,)?) => {
+        impl VInsn {
+            $(
+                pub fn $name(vd: VReg, vs2: VReg, vs1: $vs1, _mask: Mask) -> Self {
+                    Self::op($funct6, $funct3, false, vd.field(), vs2.field(), vs1.field())
+                }
+            )*
+        }
+    };
+}
+
+/// `op vd, vs2, vs1/rs1/imm` with vm = 1 and no masked form.
+macro_rules! unmasked {
+    ($funct3:expr, $vs1:ty; $($name:ident = $funct6:expr),* $(,)?) => {
+        impl VInsn {
+            $(
+                pub fn $name(vd: VReg, vs2: VReg, vs1: $vs1) -> Self {
+                    Self::op($funct6, $funct3, true, vd.field(), vs2.field(), vs1.field())
+                        .unmaskable()
+                }
+            )*
+        }
+    };
+}
+
+/// `op vd, vs2` with the operation selected by the vs1 field.
+macro_rules! unary {
+    ($funct3:expr, $funct6:expr, $vd:ty; $($name:ident = $vs1:expr),* $(,)?) => {
+        impl VInsn {
+            $(
+                pub fn $name(vd: $vd, vs2: VReg) -> Self {
+                    Self::op($funct6, $funct3, true, vd.field(), vs2.field(), $vs1)
+                }
+            )*
+        }
+    };
+}
+
+// OPIVV
+binary! { OPIVV, VReg, VReg;
+    vadd_vv = 0b000000, vsub_vv = 0b000010,
+    vminu_vv = 0b000100, vmin_vv = 0b000101, vmaxu_vv = 0b000110, vmax_vv = 0b000111,
+    vand_vv = 0b001001, vor_vv = 0b001010, vxor_vv = 0b001011,
+    vrgather_vv = 0b001100, vrgatherei16_vv = 0b001110,
+    vmseq_vv = 0b011000, vmsne_vv = 0b011001, vmsltu_vv = 0b011010, vmslt_vv = 0b011011,
+    vmsleu_vv = 0b011100, vmsle_vv = 0b011101,
+    vsaddu_vv = 0b100000, vsadd_vv = 0b100001, vssubu_vv = 0b100010, vssub_vv = 0b100011,
+    vsll_vv = 0b100101, vsmul_vv = 0b100111, vsrl_vv = 0b101000, vsra_vv = 0b101001,
+    vssrl_vv = 0b101010, vssra_vv = 0b101011,
+    vnsrl_wv = 0b101100, vnsra_wv = 0b101101, vnclipu_wv = 0b101110, vnclip_wv = 0b101111,
+    vwredsumu_vs = 0b110000, vwredsum_vs = 0b110001,
+}
+carry! { OPIVV, VReg;
+    vadc_vvm = 0b010000, vmadc_vvm = 0b010001, vsbc_vvm = 0b010010, vmsbc_vvm = 0b010011,
+    vmerge_vvm = 0b010111,
+}
+unmasked! { OPIVV, VReg; vmadc_vv = 0b010001, vmsbc_vv = 0b010011 }
+
+// OPIVX
+binary! { OPIVX, VReg, XReg;
+    vadd_vx = 0b000000, vsub_vx = 0b000010, vrsub_vx = 0b000011,
+    vminu_vx = 0b000100, vmin_vx = 0b000101, vmaxu_vx = 0b000110, vmax_vx = 0b000111,
+    vand_vx = 0b001001, vor_vx = 0b001010, vxor_vx = 0b001011,
+    vrgather_vx = 0b001100, vslideup_vx = 0b001110, vslidedown_vx = 0b001111,
+    vmseq_vx = 0b011000, vmsne_vx = 0b011001, vmsltu_vx = 0b011010, vmslt_vx = 0b011011,
+    vmsleu_vx = 0b011100, vmsle_vx = 0b011101, vmsgtu_vx = 0b011110, vmsgt_vx = 0b011111,
+    vsaddu_vx = 0b100000, vsadd_vx = 0b100001, vssubu_vx = 0b100010, vssub_vx = 0b100011,
+    vsll_vx = 0b100101, vsmul_vx = 0b100111, vsrl_vx = 0b101000, vsra_vx = 0b101001,
+    vssrl_vx = 0b101010, vssra_vx = 0b101011,
+    vnsrl_wx = 0b101100, vnsra_wx = 0b101101, vnclipu_wx = 0b101110, vnclip_wx = 0b101111,
+}
+carry! { OPIVX, XReg;
+    vadc_vxm = 0b010000, vmadc_vxm = 0b010001, vsbc_vxm = 0b010010, vmsbc_vxm = 0b010011,
+    vmerge_vxm = 0b010111,
+}
+unmasked! { OPIVX, XReg; vmadc_vx = 0b010001, vmsbc_vx = 0b010011 }
+
+// OPIVI
+binary! { OPIVI, VReg, i8;
+    vadd_vi = 0b000000, vrsub_vi = 0b000011,
+    vand_vi = 0b001001, vor_vi = 0b001010, vxor_vi = 0b001011,
+    vmseq_vi = 0b011000, vmsne_vi = 0b011001, vmsleu_vi = 0b011100, vmsle_vi = 0b011101,
+    vmsgtu_vi = 0b011110, vmsgt_vi = 0b011111,
+    vsaddu_vi = 0b100000, vsadd_vi = 0b100001,
+}
+binary! { OPIVI, VReg, u8;
+    vrgather_vi = 0b001100, vslideup_vi = 0b001110, vslidedown_vi = 0b001111,
+    vsll_vi = 0b100101, vsrl_vi = 0b101000, vsra_vi = 0b101001,
+    vssrl_vi = 0b101010, vssra_vi = 0b101011,
+    vnsrl_wi = 0b101100, vnsra_wi = 0b101101, vnclipu_wi = 0b101110, vnclip_wi = 0b101111,
+}
+carry! { OPIVI, i8; vadc_vim = 0b010000, vmadc_vim = 0b010001, vmerge_vim = 0b010111 }
+unmasked! { OPIVI, i8; vmadc_vi = 0b010001 }
+
+// OPMVV
+binary! { OPMVV, VReg, VReg;
+    vredsum_vs = 0b000000, vredand_vs = 0b000001, vredor_vs = 0b000010, vredxor_vs = 0b000011,
+    vredminu_vs = 0b000100, vredmin_vs = 0b000101, vredmaxu_vs = 0b000110, vredmax_vs = 0b000111,
+    vaaddu_vv = 0b001000, vaadd_vv = 0b001001, vasubu_vv = 0b001010, vasub_vv = 0b001011,
+    vdivu_vv = 0b100000, vdiv_vv = 0b100001, vremu_vv = 0b100010, vrem_vv = 0b100011,
+    vmulhu_vv = 0b100100, vmul_vv = 0b100101, vmulhsu_vv = 0b100110, vmulh_vv = 0b100111,
+    vwaddu_vv = 0b110000, vwadd_vv = 0b110001, vwsubu_vv = 0b110010, vwsub_vv = 0b110011,
+    vwaddu_wv = 0b110100, vwadd_wv = 0b110101, vwsubu_wv = 0b110110, vwsub_wv = 0b110111,
+    vwmulu_vv = 0b111000, vwmulsu_vv = 0b111010, vwmul_vv = 0b111011,
+}
+ternary! { OPMVV, VReg;
+    vmadd_vv = 0b101001, vnmsub_vv = 0b101011, vmacc_vv = 0b101101, vnmsac_vv = 0b101111,
+    vwmaccu_vv = 0b111100, vwmacc_vv = 0b111101, vwmaccsu_vv = 0b111111,
+}
+unmasked! { OPMVV, VReg;
+    vcompress_vm = 0b010111,
+    vmandn_mm = 0b011000, vmand_mm = 0b011001, vmor_mm = 0b011010, vmxor_mm = 0b011011,
+    vmorn_mm = 0b011100, vmnand_mm = 0b011101, vmnor_mm = 0b011110, vmxnor_mm = 0b011111,
+}
+unary! { OPMVV, 0b010000, XReg; vcpop_m = 0b10000, vfirst_m = 0b10001 }
+unary! { OPMVV, 0b010010, VReg;
+    vzext_vf8 = 0b00010, vsext_vf8 = 0b00011, vzext_vf4 = 0b00100, vsext_vf4 = 0b00101,
+    vzext_vf2 = 0b00110, vsext_vf2 = 0b00111,
+}
+unary! { OPMVV, 0b010100, VReg;
+    vmsbf_m = 0b00001, vmsof_m = 0b00010, vmsif_m = 0b00011, viota_m = 0b10000,
+}
+
+// OPMVX
+binary! { OPMVX, VReg, XReg;
+    vaaddu_vx = 0b001000, vaadd_vx = 0b001001, vasubu_vx = 0b001010, vasub_vx = 0b001011,
+    vslide1up_vx = 0b001110, vslide1down_vx = 0b001111,
+    vdivu_vx = 0b100000, vdiv_vx = 0b100001, vremu_vx = 0b100010, vrem_vx = 0b100011,
+    vmulhu_vx = 0b100100, vmul_vx = 0b100101, vmulhsu_vx = 0b100110, vmulh_vx = 0b100111,
+    vwaddu_vx = 0b110000, vwadd_vx = 0b110001, vwsubu_vx = 0b110010, vwsub_vx = 0b110011,
+    vwaddu_wx = 0b110100, vwadd_wx = 0b110101, vwsubu_wx = 0b110110, vwsub_wx = 0b110111,
+    vwmulu_vx = 0b111000, vwmulsu_vx = 0b111010, vwmul_vx = 0b111011,
+}
+ternary! { OPMVX, XReg;
+    vmadd_vx = 0b101001, vnmsub_vx = 0b101011, vmacc_vx = 0b101101, vnmsac_vx = 0b101111,
+    vwmaccu_vx = 0b111100, vwmacc_vx = 0b111101, vwmaccus_vx = 0b111110, vwmaccsu_vx = 0b111111,
+}
+
+// OPFVV
+binary! { OPFVV, VReg, VReg;
+    vfadd_vv = 0b000000, vfredusum_vs = 0b000001, vfsub_vv = 0b000010, vfredosum_vs = 0b000011,
+    vfmin_vv = 0b000100, vfredmin_vs = 0b000101, vfmax_vv = 0b000110, vfredmax_vs = 0b000111,
+    vfsgnj_vv = 0b001000, vfsgnjn_vv = 0b001001, vfsgnjx_vv = 0b001010,
+    vmfeq_vv = 0b011000, vmfle_vv = 0b011001, vmflt_vv = 0b011011, vmfne_vv = 0b011100,
+    vfdiv_vv = 0b100000, vfmul_vv = 0b100100,
+    vfwadd_vv = 0b110000, vfwredusum_vs = 0b110001, vfwsub_vv = 0b110010, vfwredosum_vs = 0b110011,
+    vfwadd_wv = 0b110100, vfwsub_wv = 0b110110, vfwmul_vv = 0b111000,
+}
+ternary! { OPFVV, VReg;
+    vfmadd_vv = 0b101000, vfnmadd_vv = 0b101001, vfmsub_vv = 0b101010, vfnmsub_vv = 0b101011,
+    vfmacc_vv = 0b101100, vfnmacc_vv = 0b101101, vfmsac_vv = 0b101110, vfnmsac_vv = 0b101111,
+    vfwmacc_vv = 0b111100, vfwnmacc_vv = 0b111101, vfwmsac_vv = 0b111110, vfwnmsac_vv = 0b111111,
+}
+unary! { OPFVV, 0b010010, VReg;
+    vfcvt_xu_f_v = 0b00000, vfcvt_x_f_v = 0b00001, vfcvt_f_xu_v = 0b00010, vfcvt_f_x_v = 0b00011,
+    vfcvt_rtz_xu_f_v = 0b00110, vfcvt_rtz_x_f_v = 0b00111,
+    vfwcvt_xu_f_v = 0b01000, vfwcvt_x_f_v = 0b01001, vfwcvt_f_xu_v = 0b01010,
+    vfwcvt_f_x_v = 0b01011, vfwcvt_f_f_v = 0b01100,
+    vfwcvt_rtz_xu_f_v = 0b01110, vfwcvt_rtz_x_f_v = 0b01111,
+    vfncvt_xu_f_w = 0b10000, vfncvt_x_f_w = 0b10001, vfncvt_f_xu_w = 0b10010,
+    vfncvt_f_x_w = 0b10011, vfncvt_f_f_w = 0b10100, vfncvt_rod_f_f_w = 0b10101,
+    vfncvt_rtz_xu_f_w = 0b10110, vfncvt_rtz_x_f_w = 0b10111,
+}
+unary! { OPFVV, 0b010011, VReg;
+    vfsqrt_v = 0b00000, vfrsqrt7_v = 0b00100, vfrec7_v = 0b00101, vfclass_v = 0b10000,
+}
+
+// OPFVF
+binary! { OPFVF, VReg, FReg;
+    vfadd_vf = 0b000000, vfsub_vf = 0b000010, vfmin_vf = 0b000100, vfmax_vf = 0b000110,
+    vfsgnj_vf = 0b001000, vfsgnjn_vf = 0b001001, vfsgnjx_vf = 0b001010,
+    vfslide1up_vf = 0b001110, vfslide1down_vf = 0b001111,
+    vmfeq_vf = 0b011000, vmfle_vf = 0b011001, vmflt_vf = 0b011011, vmfne_vf = 0b011100,
+    vmfgt_vf = 0b011101, vmfge_vf = 0b011111,
+    vfdiv_vf = 0b100000, vfrdiv_vf = 0b100001, vfmul_vf = 0b100100, vfrsub_vf = 0b100111,
+    vfwadd_vf = 0b110000, vfwsub_vf = 0b110010, vfwadd_wf = 0b110100, vfwsub_wf = 0b110110,
+    vfwmul_vf = 0b111000,
+}
+ternary! { OPFVF, FReg;
+    vfmadd_vf = 0b101000, vfnmadd_vf = 0b101001, vfmsub_vf = 0b101010, vfnmsub_vf = 0b101011,
+    vfmacc_vf = 0b101100, vfnmacc_vf = 0b101101, vfmsac_vf = 0b101110, vfnmsac_vf = 0b101111,
+    vfwmacc_vf = 0b111100, vfwnmacc_vf = 0b111101, vfwmsac_vf = 0b111110, vfwnmsac_vf = 0b111111,
+}
+carry! { OPFVF, FReg; vfmerge_vfm = 0b010111 }
+
+/// Moves and the remaining forms whose operands don't fit the tables above.
+impl VInsn {
+    pub fn vmv_v_v(vd: VReg, vs1: VReg) -> Self {
+        Self::op(0b010111, OPIVV, true, vd.field(), 0, vs1.field()).unmaskable()
+    }
+
+    pub fn vmv_v_x(vd: VReg, rs1: XReg) -> Self {
+        Self::op(0b010111, OPIVX, true, vd.field(), 0, rs1.field()).unmaskable()
+    }
+
+    pub fn vmv_v_i(vd: VReg, imm: i8) -> Self {
+        Self::op(0b010111, OPIVI, true, vd.field(), 0, imm.field()).unmaskable()
+    }
+
+    pub fn vfmv_v_f(vd: VReg, rs1: FReg) -> Self {
+        Self::op(0b010111, OPFVF, true, vd.field(), 0, rs1.field()).unmaskable()
+    }
+
+    pub fn vmv_x_s(rd: XReg, vs2: VReg) -> Self {
+        Self::op(0b010000, OPMVV, true, rd.field(), vs2.field(), 0).unmaskable()
+    }
+
+    pub fn vmv_s_x(vd: VReg, rs1: XReg) -> Self {
+        Self::op(0b010000, OPMVX, true, vd.field(), 0, rs1.field()).unmaskable()
+    }
+
+    pub fn vfmv_f_s(rd: FReg, vs2: VReg) -> Self {
+        Self::op(0b010000, OPFVV, true, rd.field(), vs2.field(), 0).unmaskable()
+    }
+
+    pub fn vfmv_s_f(vd: VReg, rs1: FReg) -> Self {
+        Self::op(0b010000, OPFVF, true, vd.field(), 0, rs1.field()).unmaskable()
+    }
+
+    pub fn vid_v(vd: VReg) -> Self {
+        Self::op(0b010100, OPMVV, true, vd.field(), 0, 0b10001)
+    }
+}
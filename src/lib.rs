@@ -1,78 +1,215 @@
-#[link(name = "spike-interfaces", kind = "static")]
-extern "C" {
-    pub fn rvv_new_processor(vlen: u32, elen: u32, mem_size: u64) -> u64;
-    pub fn rvv_execute(processor: u64, instruction: u64) -> i32;
-    /**
-     *  offset: the offset in register file in bytes
-     *
-     *  return: 0, success; otherwise, failed. Same to other functions
-     */
-    pub fn rvv_get_vreg(processor: u64, offset: u64, mem: *const u8, mem_size: u64) -> i32;
-    pub fn rvv_set_vreg(processor: u64, offset: u64, mem: *const u8, mem_size: u64) -> i32;
-    pub fn rvv_get_xreg(processor: u64, index: u64, content: *mut u64) -> i32;
-    pub fn rvv_set_xreg(processor: u64, index: u64, content: u64) -> i32;
-
-    pub fn rvv_get_vlen(processor: u64) -> u64;
-    pub fn rvv_get_elen(processor: u64) -> u64;
-    pub fn rvv_get_vl(processor: u64) -> u64;
-    pub fn rvv_get_sew(processor: u64) -> u64;
-    pub fn rvv_get_vtype(processor: u64) -> u64;
-    pub fn rvv_get_lmul(processor: u64) -> f32;
-    pub fn rvv_get_vill(processor: u64) -> u64;
-    pub fn rvv_load_mem(processor: u64, addr: u64, len: u64, bytes: *const u8) -> i32;
-    pub fn rvv_store_mem(processor: u64, addr: u64, len: u64, bytes: *const u8) -> i32;
-
-    pub fn rvv_delete_processor(processor: u64);
-
-    pub fn rvv_new_disassembler(xlen: u32) -> u64;
-    pub fn rvv_disassemble(dis: u64, inst: u64, output: *mut u8, output_len: *mut u32) -> i32;
-    pub fn rvv_delete_disassembler(dis: u64);
+pub mod asm;
+#[cfg(feature = "ckb-vm")]
+pub mod ckbvm;
+pub mod config;
+pub mod coverage;
+pub mod decode;
+pub mod diagnostics;
+pub mod disasm;
+pub mod ffi;
+#[cfg(feature = "gdb")]
+pub mod gdb;
+pub mod generator;
+pub mod insn;
+pub mod model;
+pub mod opcodes;
+pub mod pool;
+pub mod repl;
+pub mod roundtrip;
+pub mod run;
+pub mod trace;
+pub mod tracediff;
+pub mod vtype;
+
+pub use config::ConfigError;
+pub use coverage::Coverage;
+pub use model::Model;
+pub use pool::SpikePool;
+
+use std::marker::PhantomData;
+
+use ffi::*;
+
+/// The riscv-isa-sim version the shim was built against. The build script
+/// warns if it isn't one of the versions the shim is known to work with.
+pub fn spike_version() -> &'static str {
+    unsafe { std::ffi::CStr::from_ptr(rvv_spike_version()) }
+        .to_str()
+        .unwrap()
 }
 
+/// An error code returned by the shim: the trap cause plus one if an
+/// instruction trapped, negative otherwise.
 #[derive(Debug)]
-pub struct Error(i32);
+pub struct Error {
+    code: i32,
+    /// Why the shim rejected the configuration or which C++ exception it
+    /// caught.
+    message: Option<String>,
+}
+
+impl Error {
+    /// `code` must have just been returned by the shim on this thread.
+    pub(crate) fn from_code(code: i32) -> Self {
+        let message = if code == RVV_ERROR_INVALID_CONFIG || code == RVV_ERROR_EXCEPTION {
+            let message = unsafe { std::ffi::CStr::from_ptr(rvv_last_error()) };
+            Some(message.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+        Self { code, message }
+    }
+
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The trap cause (`CAUSE_*` in riscv/encoding.h) if an instruction trapped.
+    pub fn trap_cause(&self) -> Option<u64> {
+        if self.code > 0 {
+            Some(self.code as u64 - 1)
+        } else {
+            None
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "error({})", self.0)
+        write!(f, "error({})", self.code)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
     }
 }
 impl std::error::Error for Error {}
 
+#[derive(Debug)]
+pub struct BatchResult {
+    /// The number of instructions executed without trapping.
+    pub retired: usize,
+    /// The error of the instruction at index `retired` if it trapped.
+    pub trap: Option<Error>,
+}
+
+impl BatchResult {
+    pub fn is_ok(&self) -> bool {
+        self.trap.is_none()
+    }
+}
+
+/// A Spike processor together with its memory.
+///
+/// `Spike` is `Send`, so processors can be created on one thread and run on
+/// another, e.g. one per worker of a thread pool. It isn't `Sync`: every
+/// method mutates the processor through `&self`.
 pub struct Spike {
     addr: u64,
+    _not_sync: PhantomData<*mut ()>,
 }
 
+// SAFETY: the handle owns everything the processor touches while executing,
+// and nothing it shares with other processors is mutable:
+//
+// - processor_t, its mmu_t and register files, and the shim's memory are
+//   allocated per processor in rvv_new_processor and freed together.
+// - softfloat keeps the rounding mode and exception flags in globals, but they
+//   are THREAD_LOCAL in Spike's build of softfloat, and Spike sets the rounding
+//   mode before and collects the flags after each instruction, so nothing
+//   carries over when a processor moves between threads.
+// - Spike's instruction and extension tables are filled in during static
+//   initialization or the processor's constructor and only read afterwards;
+//   the shim's opcode table is const.
+// - Diagnostics go through a callback held in an atomic, see `diagnostics`;
+//   the shim formats each line into a buffer of its own before passing it on.
+//
+// See tests/threads.rs.
+unsafe impl Send for Spike {}
+
 impl Spike {
-    pub fn new(vlen: u32, elen: u32, mem_size: u64) -> Self {
-        unsafe {
-            Self {
-                addr: rvv_new_processor(vlen, elen, mem_size),
-            }
+    /// A processor with `mem_size` bytes of memory at
+    /// [`START_MEM`](config::START_MEM). The configuration is checked with
+    /// [`config::check`] first.
+    pub fn new(vlen: u32, elen: u32, mem_size: u64) -> Result<Self, ConfigError> {
+        Self::with_isa(config::DEFAULT_ISA, vlen, elen, mem_size)
+    }
+
+    /// Like [`new`](Self::new) with another ISA string, e.g. `RV64IMACV`.
+    /// It's checked with [`config::check_isa`] first, then parsed by Spike.
+    pub fn with_isa(isa: &str, vlen: u32, elen: u32, mem_size: u64) -> Result<Self, ConfigError> {
+        config::check_isa(isa)?;
+        config::check(vlen, elen, mem_size)?;
+        diagnostics::install();
+        let isa = std::ffi::CString::new(isa).unwrap();
+        let mut addr = 0;
+        let r = unsafe { rvv_new_processor_isa(isa.as_ptr(), vlen, elen, mem_size, &mut addr) };
+        if r != 0 {
+            return Err(Error::from_code(r).into());
         }
+        Ok(Self {
+            addr,
+            _not_sync: PhantomData,
+        })
+    }
+
+    /// Reset to the state of a newly created processor: registers zeroed,
+    /// CSRs (including vtype) at their reset values, memory written since
+    /// the last reset cleared and breakpoints, watchpoints and conditions
+    /// removed. Returns the number of bytes of memory cleared. Commit logging
+    /// stays enabled, see [`enable_commit_log`](Self::enable_commit_log).
+    pub fn reset(&self) -> u64 {
+        unsafe { rvv_reset_processor(self.addr) }
     }
 
     pub fn execute(&self, instruction: u64) -> Result<(), Error> {
         let r = unsafe { rvv_execute(self.addr, instruction) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
     }
 
-    pub fn get_vreg(&self, offset: u64, mem: *const u8, mem_size: u64) -> Result<(), Error> {
-        let r = unsafe { rvv_get_vreg(self.addr, offset, mem, mem_size) };
+    /// Execute `insns` in order within a single FFI call, stopping at the
+    /// first instruction that traps.
+    pub fn execute_batch(&self, insns: &[u32]) -> BatchResult {
+        let mut retired = 0;
+        let r = unsafe {
+            rvv_execute_batch(self.addr, insns.as_ptr(), insns.len() as u64, &mut retired)
+        };
+        BatchResult {
+            retired: retired as usize,
+            trap: if r != 0 {
+                Some(Error::from_code(r))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Read `buf.len()` bytes of the vector register file starting at byte
+    /// `offset`.
+    pub fn get_vreg(&self, offset: u64, buf: &mut [u8]) -> Result<(), Error> {
+        check_range(offset, buf.len(), -1)?;
+        let r = unsafe { rvv_get_vreg(self.addr, offset, buf.as_mut_ptr(), buf.len() as u64) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
     }
 
-    pub fn set_vreg(&self, offset: u64, mem: *const u8, mem_size: u64) -> Result<(), Error> {
-        let r = unsafe { rvv_set_vreg(self.addr, offset, mem, mem_size) };
+    /// Write `buf` into the vector register file starting at byte `offset`.
+    pub fn set_vreg(&self, offset: u64, buf: &[u8]) -> Result<(), Error> {
+        check_range(offset, buf.len(), -1)?;
+        let r = unsafe { rvv_set_vreg(self.addr, offset, buf.as_ptr(), buf.len() as u64) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
@@ -82,7 +219,7 @@ impl Spike {
         let mut x = 0;
         let r = unsafe { rvv_get_xreg(self.addr, index, &mut x) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(x)
         }
@@ -91,7 +228,49 @@ impl Spike {
     pub fn set_xreg(&self, index: u64, content: u64) -> Result<(), Error> {
         let r = unsafe { rvv_set_xreg(self.addr, index, content) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The low 64 bits of f`index`.
+    pub fn get_freg(&self, index: u64) -> Result<u64, Error> {
+        let mut f = 0;
+        let r = unsafe { rvv_get_freg(self.addr, index, &mut f) };
+        if r != 0 {
+            Err(Error::from_code(r))
+        } else {
+            Ok(f)
+        }
+    }
+
+    /// Write f`index`, NaN-boxing `content`.
+    pub fn set_freg(&self, index: u64, content: u64) -> Result<(), Error> {
+        let r = unsafe { rvv_set_freg(self.addr, index, content) };
+        if r != 0 {
+            Err(Error::from_code(r))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Read CSR number `which`. A CSR the processor doesn't have is an
+    /// illegal instruction trap, see [`Error::trap_cause`].
+    pub fn get_csr(&self, which: u64) -> Result<u64, Error> {
+        let mut x = 0;
+        let r = unsafe { rvv_get_csr(self.addr, which, &mut x) };
+        if r != 0 {
+            Err(Error::from_code(r))
+        } else {
+            Ok(x)
+        }
+    }
+
+    pub fn set_csr(&self, which: u64, content: u64) -> Result<(), Error> {
+        let r = unsafe { rvv_set_csr(self.addr, which, content) };
+        if r != 0 {
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
@@ -125,23 +304,42 @@ impl Spike {
         unsafe { rvv_get_vill(self.addr) }
     }
 
-    pub fn load_mem(&self, addr: u64, len: u64, bytes: *const u8) -> Result<(), Error> {
-        let r = unsafe { rvv_load_mem(self.addr, addr, len, bytes) };
+    /// Read `buf.len()` bytes of memory at `addr`.
+    pub fn load_mem(&self, addr: u64, buf: &mut [u8]) -> Result<(), Error> {
+        check_range(addr, buf.len(), -2)?;
+        let r = unsafe { rvv_load_mem(self.addr, addr, buf.len() as u64, buf.as_mut_ptr()) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
     }
 
-    pub fn store_mem(&self, addr: u64, len: u64, bytes: *const u8) -> Result<(), Error> {
-        let r = unsafe { rvv_store_mem(self.addr, addr, len, bytes) };
+    /// Write `buf` to memory at `addr`.
+    pub fn store_mem(&self, addr: u64, buf: &[u8]) -> Result<(), Error> {
+        check_range(addr, buf.len(), -2)?;
+        let r = unsafe { rvv_store_mem(self.addr, addr, buf.len() as u64, buf.as_ptr()) };
         if r != 0 {
-            Err(Error(r))
+            Err(Error::from_code(r))
         } else {
             Ok(())
         }
     }
+
+    /// The bytes of memory at [`START_MEM`](config::START_MEM), 0 without
+    /// memory.
+    pub fn get_mem_size(&self) -> u64 {
+        unsafe { rvv_get_mem_size(self.addr) }
+    }
+}
+
+/// Fail with the shim's out-of-bounds `code` if `start + len` overflows, so
+/// the shim never sees a range that wraps around.
+fn check_range(start: u64, len: usize, code: i32) -> Result<(), Error> {
+    match start.checked_add(len as u64) {
+        Some(_) => Ok(()),
+        None => Err(Error::from_code(code)),
+    }
 }
 
 impl Drop for Spike {
@@ -0,0 +1,146 @@
+use crate::{Error, Spike};
+
+/// The common interface of the RISC-V models compared by the fuzz harnesses.
+///
+/// Vector registers are always accessed as a whole, so `buf` must be
+/// exactly `vlen() / 8` bytes long.
+pub trait Model {
+    type Error: std::error::Error;
+
+    fn execute(&mut self, insn: u32) -> Result<(), Self::Error>;
+
+    fn vlen(&self) -> u64;
+    fn vl(&self) -> u64;
+    fn sew(&self) -> u64;
+    fn vtype(&self) -> u64;
+    fn vill(&self) -> bool;
+
+    fn xreg(&self, index: usize) -> Result<u64, Self::Error>;
+    fn set_xreg(&mut self, index: usize, value: u64) -> Result<(), Self::Error>;
+    fn vreg(&mut self, index: usize, buf: &mut [u8]) -> Result<(), Self::Error>;
+    fn set_vreg(&mut self, index: usize, buf: &[u8]) -> Result<(), Self::Error>;
+
+    fn load_mem(&mut self, addr: u64, buf: &mut [u8]) -> Result<(), Self::Error>;
+    fn store_mem(&mut self, addr: u64, buf: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl Model for Spike {
+    type Error = Error;
+
+    fn execute(&mut self, insn: u32) -> Result<(), Error> {
+        Spike::execute(self, insn as u64)
+    }
+
+    fn vlen(&self) -> u64 {
+        self.get_vlen()
+    }
+
+    fn vl(&self) -> u64 {
+        self.get_vl()
+    }
+
+    fn sew(&self) -> u64 {
+        self.get_sew()
+    }
+
+    fn vtype(&self) -> u64 {
+        self.get_vtype()
+    }
+
+    fn vill(&self) -> bool {
+        self.get_vill() != 0
+    }
+
+    fn xreg(&self, index: usize) -> Result<u64, Error> {
+        self.get_xreg(index as u64)
+    }
+
+    fn set_xreg(&mut self, index: usize, value: u64) -> Result<(), Error> {
+        Spike::set_xreg(self, index as u64, value)
+    }
+
+    fn vreg(&mut self, index: usize, buf: &mut [u8]) -> Result<(), Error> {
+        let vlenb = self.get_vlen() / 8;
+        assert_eq!(buf.len() as u64, vlenb);
+        self.get_vreg(vlenb * index as u64, buf)
+    }
+
+    fn set_vreg(&mut self, index: usize, buf: &[u8]) -> Result<(), Error> {
+        let vlenb = self.get_vlen() / 8;
+        assert_eq!(buf.len() as u64, vlenb);
+        Spike::set_vreg(self, vlenb * index as u64, buf)
+    }
+
+    fn load_mem(&mut self, addr: u64, buf: &mut [u8]) -> Result<(), Error> {
+        Spike::load_mem(self, addr, buf)
+    }
+
+    fn store_mem(&mut self, addr: u64, buf: &[u8]) -> Result<(), Error> {
+        Spike::store_mem(self, addr, buf)
+    }
+}
+
+/// Copy the architectural state (x registers, v registers, vtype and vl) of
+/// `from` into `to`. Both models must have the same VLEN.
+///
+/// vtype and vl are restored by running `vsetvl x0, x1, x2` on `to`, with the
+/// two scratch registers written back afterwards.
+pub fn sync_state<A: Model, B: Model>(from: &mut A, to: &mut B) -> Result<(), SyncError<A, B>> {
+    assert_eq!(from.vlen(), to.vlen());
+    let vl = from.vl();
+    let vtype = from.vtype();
+    to.set_xreg(1, vl).map_err(SyncError::To)?;
+    to.set_xreg(2, vtype).map_err(SyncError::To)?;
+    // vsetvl x0, x1, x2
+    to.execute(0b1000000_00010_00001_111_00000_1010111)
+        .map_err(SyncError::To)?;
+
+    for i in 1..32 {
+        let x = from.xreg(i).map_err(SyncError::From)?;
+        to.set_xreg(i, x).map_err(SyncError::To)?;
+    }
+    let mut buf = vec![0u8; from.vlen() as usize / 8];
+    for i in 0..32 {
+        from.vreg(i, &mut buf).map_err(SyncError::From)?;
+        to.set_vreg(i, &buf).map_err(SyncError::To)?;
+    }
+    Ok(())
+}
+
+/// Copy `len` bytes of memory starting at `addr` from `from` into `to`.
+pub fn sync_memory<A: Model, B: Model>(
+    from: &mut A,
+    to: &mut B,
+    addr: u64,
+    len: usize,
+) -> Result<(), SyncError<A, B>> {
+    let mut buf = vec![0u8; len];
+    from.load_mem(addr, &mut buf).map_err(SyncError::From)?;
+    to.store_mem(addr, &buf).map_err(SyncError::To)
+}
+
+/// Failure while copying state between two models, tagged with the side that failed.
+pub enum SyncError<A: Model, B: Model> {
+    From(A::Error),
+    To(B::Error),
+}
+
+impl<A: Model, B: Model> std::fmt::Debug for SyncError<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::From(e) => write!(f, "From({:?})", e),
+            SyncError::To(e) => write!(f, "To({:?})", e),
+        }
+    }
+}
+
+impl<A: Model, B: Model> std::fmt::Display for SyncError<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::From(e) => write!(f, "reading source model: {}", e),
+            SyncError::To(e) => write!(f, "writing target model: {}", e),
+        }
+    }
+}
+
+impl<A: Model, B: Model> std::error::Error for SyncError<A, B> {}
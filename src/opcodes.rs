@@ -0,0 +1,809 @@
+//! Encoding table of the integer RVV instructions exercised by the fuzz harnesses.
+//!
+//! Each entry records the fixed bits of the encoding (`bits`/`mask`, like the
+//! `MATCH_`/`MASK_` pairs of riscv-opcodes) and what every variable field holds,
+//! which is enough to generate, build and classify instructions.
+
+/// How many vector registers an operand occupies, relative to the current vtype.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emul {
+    /// A single register, e.g. a mask or the scalar operand of a reduction.
+    One,
+    /// EMUL = LMUL.
+    Lmul,
+    /// EMUL = 2 * LMUL, EEW = 2 * SEW.
+    Wide,
+    /// EMUL = LMUL / n, EEW = SEW / n.
+    Frac(u8),
+    /// EMUL = EEW / SEW * LMUL for an explicit EEW (memory and index operands).
+    Eew(u16),
+    /// A fixed number of registers, independent of vtype.
+    Regs(u8),
+}
+
+/// What a variable register/immediate field of an encoding holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// The field is part of the opcode and holds this value.
+    Fixed(u8),
+    /// Any x register.
+    X,
+    /// The x register holding a memory base address.
+    Base,
+    /// The x register holding a memory stride.
+    Stride,
+    Simm5,
+    Uimm5,
+    V(Emul),
+}
+
+/// How the `vm` bit is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vm {
+    /// Masked or unmasked, chosen freely.
+    Free,
+    /// Always 0: v0 is an input (carry or merge mask).
+    Masked,
+    /// Always 1.
+    Unmasked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InsnClass {
+    Arith,
+    Widening,
+    Narrowing,
+    Mask,
+    Permutation,
+    Load,
+    Store,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Opcode {
+    pub name: &'static str,
+    pub bits: u32,
+    pub mask: u32,
+    pub class: InsnClass,
+    /// vd, vs3 (stores) or rd, bits 11:7.
+    pub vd: Operand,
+    /// vs2 or rs2, bits 24:20.
+    pub vs2: Operand,
+    /// vs1, rs1 or the immediate, bits 19:15.
+    pub vs1: Operand,
+    pub vm: Vm,
+    /// vd is a mask register, so it may be v0 even when the instruction is masked.
+    pub mask_dest: bool,
+    /// The destination group must not overlap any vector source group.
+    ///
+    /// The specification allows some partial overlaps for widening, narrowing
+    /// and mask-producing instructions; they are treated as reserved here.
+    pub disjoint: bool,
+}
+
+impl Opcode {
+    pub fn matches(&self, insn: u32) -> bool {
+        insn & self.mask == self.bits
+    }
+}
+
+pub fn find(insn: u32) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|o| o.matches(insn))
+}
+
+pub fn find_by_name(name: &str) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|o| o.name == name)
+}
+
+const OP_V: u32 = 0b1010111;
+const LOAD_FP: u32 = 0b0000111;
+const STORE_FP: u32 = 0b0100111;
+
+const OPIVV: u32 = 0b000;
+const OPMVV: u32 = 0b010;
+const OPIVI: u32 = 0b011;
+const OPIVX: u32 = 0b100;
+const OPMVX: u32 = 0b110;
+
+const L: Operand = Operand::V(Emul::Lmul);
+const W: Operand = Operand::V(Emul::Wide);
+const ONE: Operand = Operand::V(Emul::One);
+const X: Operand = Operand::X;
+
+const fn field_mask(op: Operand) -> u32 {
+    match op {
+        Operand::Fixed(_) => 0b11111,
+        _ => 0,
+    }
+}
+
+const fn field_bits(op: Operand) -> u32 {
+    match op {
+        Operand::Fixed(v) => v as u32,
+        _ => 0,
+    }
+}
+
+const fn src1(funct3: u32, v: Operand) -> Operand {
+    match funct3 {
+        OPIVX | OPMVX => X,
+        OPIVI => Operand::Simm5,
+        _ => v,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn opv(
+    name: &'static str,
+    class: InsnClass,
+    funct6: u32,
+    funct3: u32,
+    vd: Operand,
+    vs2: Operand,
+    vs1: Operand,
+    vm: Vm,
+) -> Opcode {
+    let vm_mask = match vm {
+        Vm::Free => 0,
+        _ => 1 << 25,
+    };
+    let vm_bits = match vm {
+        Vm::Unmasked => 1 << 25,
+        _ => 0,
+    };
+    Opcode {
+        name,
+        bits: funct6 << 26
+            | vm_bits
+            | field_bits(vs2) << 20
+            | field_bits(vs1) << 15
+            | funct3 << 12
+            | field_bits(vd) << 7
+            | OP_V,
+        mask: 0b111111 << 26
+            | vm_mask
+            | field_mask(vs2) << 20
+            | field_mask(vs1) << 15
+            | 0b111 << 12
+            | field_mask(vd) << 7
+            | 0b1111111,
+        class,
+        vd,
+        vs2,
+        vs1,
+        vm,
+        mask_dest: false,
+        disjoint: false,
+    }
+}
+
+/// Single-width `vd, vs2, vs1/rs1/imm` with a free mask.
+const fn arith(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    opv(
+        name,
+        InsnClass::Arith,
+        funct6,
+        funct3,
+        L,
+        L,
+        src1(funct3, L),
+        Vm::Free,
+    )
+}
+
+/// Single-width with v0 as carry-in or merge mask.
+const fn carry(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    opv(
+        name,
+        InsnClass::Arith,
+        funct6,
+        funct3,
+        L,
+        L,
+        src1(funct3, L),
+        Vm::Masked,
+    )
+}
+
+/// `vmadc`/`vmsbc`: mask destination, carry-in or not depending on `vm`.
+const fn carry_out(name: &'static str, funct6: u32, funct3: u32, vm: Vm) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Mask,
+        funct6,
+        funct3,
+        ONE,
+        L,
+        src1(funct3, L),
+        vm,
+    );
+    o.mask_dest = true;
+    o.disjoint = true;
+    o
+}
+
+const fn compare(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Mask,
+        funct6,
+        funct3,
+        ONE,
+        L,
+        src1(funct3, L),
+        Vm::Free,
+    );
+    o.mask_dest = true;
+    o.disjoint = true;
+    o
+}
+
+/// 2*SEW = SEW op SEW.
+const fn widen(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Widening,
+        funct6,
+        funct3,
+        W,
+        L,
+        src1(funct3, L),
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+/// 2*SEW = 2*SEW op SEW.
+const fn widen_w(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Widening,
+        funct6,
+        funct3,
+        W,
+        W,
+        src1(funct3, L),
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+/// SEW = 2*SEW op SEW.
+const fn narrow(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Narrowing,
+        funct6,
+        funct3,
+        L,
+        W,
+        src1(funct3, L),
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+const fn extend(name: &'static str, vs1: u8, frac: u8) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Arith,
+        0b010010,
+        OPMVV,
+        L,
+        Operand::V(Emul::Frac(frac)),
+        Operand::Fixed(vs1),
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+const fn reduce(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    opv(
+        name,
+        InsnClass::Arith,
+        funct6,
+        funct3,
+        ONE,
+        L,
+        ONE,
+        Vm::Free,
+    )
+}
+
+const fn reduce_w(name: &'static str, funct6: u32) -> Opcode {
+    opv(
+        name,
+        InsnClass::Widening,
+        funct6,
+        OPIVV,
+        ONE,
+        L,
+        ONE,
+        Vm::Free,
+    )
+}
+
+const fn mask_logical(name: &'static str, funct6: u32) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Mask,
+        funct6,
+        OPMVV,
+        ONE,
+        ONE,
+        ONE,
+        Vm::Unmasked,
+    );
+    o.mask_dest = true;
+    o
+}
+
+const fn mask_unary(name: &'static str, vs1: u8) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Mask,
+        0b010100,
+        OPMVV,
+        ONE,
+        ONE,
+        Operand::Fixed(vs1),
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+const fn permute(name: &'static str, funct6: u32, funct3: u32, vs1: Operand) -> Opcode {
+    let mut o = opv(
+        name,
+        InsnClass::Permutation,
+        funct6,
+        funct3,
+        L,
+        L,
+        vs1,
+        Vm::Free,
+    );
+    o.disjoint = true;
+    o
+}
+
+const fn slide(name: &'static str, funct6: u32, funct3: u32) -> Opcode {
+    opv(
+        name,
+        InsnClass::Permutation,
+        funct6,
+        funct3,
+        L,
+        L,
+        src1(funct3, L),
+        Vm::Free,
+    )
+}
+
+const fn whole_move(name: &'static str, nreg: u8) -> Opcode {
+    let regs = Operand::V(Emul::Regs(nreg));
+    opv(
+        name,
+        InsnClass::Permutation,
+        0b100111,
+        OPIVI,
+        regs,
+        regs,
+        Operand::Fixed(nreg - 1),
+        Vm::Unmasked,
+    )
+}
+
+/// `mew` and `width` for a memory element width.
+const fn width(eew: u16) -> u32 {
+    let mew = if eew > 64 { 1 << 28 } else { 0 };
+    let width = match eew {
+        8 | 128 => 0b000,
+        16 | 256 => 0b101,
+        32 | 512 => 0b110,
+        _ => 0b111,
+    };
+    mew | width << 12
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn mem(
+    name: &'static str,
+    store: bool,
+    nf: u32,
+    mop: u32,
+    eew: u16,
+    vd: Operand,
+    vs2: Operand,
+    vm: Vm,
+) -> Opcode {
+    let (class, opcode) = if store {
+        (InsnClass::Store, STORE_FP)
+    } else {
+        (InsnClass::Load, LOAD_FP)
+    };
+    let vm_mask = match vm {
+        Vm::Free => 0,
+        _ => 1 << 25,
+    };
+    let vm_bits = match vm {
+        Vm::Unmasked => 1 << 25,
+        _ => 0,
+    };
+    Opcode {
+        name,
+        bits: nf << 29 | mop << 26 | vm_bits | field_bits(vs2) << 20 | width(eew) | opcode,
+        mask: 0b111_1_11 << 26 | vm_mask | field_mask(vs2) << 20 | 0b111 << 12 | 0b1111111,
+        class,
+        vd,
+        vs2,
+        vs1: Operand::Base,
+        vm,
+        mask_dest: false,
+        disjoint: mop == 0b01 || mop == 0b11,
+    }
+}
+
+const fn unit(name: &'static str, store: bool, eew: u16) -> Opcode {
+    let v = Operand::V(Emul::Eew(eew));
+    mem(
+        name,
+        store,
+        0,
+        0b00,
+        eew,
+        v,
+        Operand::Fixed(0b00000),
+        Vm::Free,
+    )
+}
+
+const fn strided(name: &'static str, store: bool, eew: u16) -> Opcode {
+    let v = Operand::V(Emul::Eew(eew));
+    mem(name, store, 0, 0b10, eew, v, Operand::Stride, Vm::Free)
+}
+
+const fn indexed(name: &'static str, store: bool, ordered: bool, eew: u16) -> Opcode {
+    let mop = if ordered { 0b11 } else { 0b01 };
+    let index = Operand::V(Emul::Eew(eew));
+    mem(name, store, 0, mop, eew, L, index, Vm::Free)
+}
+
+const fn whole(name: &'static str, store: bool, nreg: u8, eew: u16) -> Opcode {
+    let v = Operand::V(Emul::Regs(nreg));
+    mem(
+        name,
+        store,
+        nreg as u32 - 1,
+        0b00,
+        eew,
+        v,
+        Operand::Fixed(0b01000),
+        Vm::Unmasked,
+    )
+}
+
+const fn mask_mem(name: &'static str, store: bool) -> Opcode {
+    mem(
+        name,
+        store,
+        0,
+        0b00,
+        8,
+        ONE,
+        Operand::Fixed(0b01011),
+        Vm::Unmasked,
+    )
+}
+
+#[rustfmt::skip]
+pub static OPCODES: &[Opcode] = &[
+    arith("vadd.vv", 0b000000, OPIVV),
+    arith("vadd.vx", 0b000000, OPIVX),
+    arith("vadd.vi", 0b000000, OPIVI),
+    arith("vsub.vv", 0b000010, OPIVV),
+    arith("vsub.vx", 0b000010, OPIVX),
+    arith("vrsub.vx", 0b000011, OPIVX),
+    arith("vrsub.vi", 0b000011, OPIVI),
+    arith("vminu.vv", 0b000100, OPIVV),
+    arith("vminu.vx", 0b000100, OPIVX),
+    arith("vmin.vv", 0b000101, OPIVV),
+    arith("vmin.vx", 0b000101, OPIVX),
+    arith("vmaxu.vv", 0b000110, OPIVV),
+    arith("vmaxu.vx", 0b000110, OPIVX),
+    arith("vmax.vv", 0b000111, OPIVV),
+    arith("vmax.vx", 0b000111, OPIVX),
+    arith("vand.vv", 0b001001, OPIVV),
+    arith("vand.vx", 0b001001, OPIVX),
+    arith("vand.vi", 0b001001, OPIVI),
+    arith("vor.vv", 0b001010, OPIVV),
+    arith("vor.vx", 0b001010, OPIVX),
+    arith("vor.vi", 0b001010, OPIVI),
+    arith("vxor.vv", 0b001011, OPIVV),
+    arith("vxor.vx", 0b001011, OPIVX),
+    arith("vxor.vi", 0b001011, OPIVI),
+    arith("vsll.vv", 0b100101, OPIVV),
+    arith("vsll.vx", 0b100101, OPIVX),
+    arith("vsll.vi", 0b100101, OPIVI),
+    arith("vsrl.vv", 0b101000, OPIVV),
+    arith("vsrl.vx", 0b101000, OPIVX),
+    arith("vsrl.vi", 0b101000, OPIVI),
+    arith("vsra.vv", 0b101001, OPIVV),
+    arith("vsra.vx", 0b101001, OPIVX),
+    arith("vsra.vi", 0b101001, OPIVI),
+    arith("vsaddu.vv", 0b100000, OPIVV),
+    arith("vsaddu.vx", 0b100000, OPIVX),
+    arith("vsaddu.vi", 0b100000, OPIVI),
+    arith("vsadd.vv", 0b100001, OPIVV),
+    arith("vsadd.vx", 0b100001, OPIVX),
+    arith("vsadd.vi", 0b100001, OPIVI),
+    arith("vssubu.vv", 0b100010, OPIVV),
+    arith("vssubu.vx", 0b100010, OPIVX),
+    arith("vssub.vv", 0b100011, OPIVV),
+    arith("vssub.vx", 0b100011, OPIVX),
+    arith("vsmul.vv", 0b100111, OPIVV),
+    arith("vsmul.vx", 0b100111, OPIVX),
+    arith("vssrl.vv", 0b101010, OPIVV),
+    arith("vssrl.vx", 0b101010, OPIVX),
+    arith("vssrl.vi", 0b101010, OPIVI),
+    arith("vssra.vv", 0b101011, OPIVV),
+    arith("vssra.vx", 0b101011, OPIVX),
+    arith("vssra.vi", 0b101011, OPIVI),
+    arith("vaaddu.vv", 0b001000, OPMVV),
+    arith("vaaddu.vx", 0b001000, OPMVX),
+    arith("vaadd.vv", 0b001001, OPMVV),
+    arith("vaadd.vx", 0b001001, OPMVX),
+    arith("vasubu.vv", 0b001010, OPMVV),
+    arith("vasubu.vx", 0b001010, OPMVX),
+    arith("vasub.vv", 0b001011, OPMVV),
+    arith("vasub.vx", 0b001011, OPMVX),
+    arith("vdivu.vv", 0b100000, OPMVV),
+    arith("vdivu.vx", 0b100000, OPMVX),
+    arith("vdiv.vv", 0b100001, OPMVV),
+    arith("vdiv.vx", 0b100001, OPMVX),
+    arith("vremu.vv", 0b100010, OPMVV),
+    arith("vremu.vx", 0b100010, OPMVX),
+    arith("vrem.vv", 0b100011, OPMVV),
+    arith("vrem.vx", 0b100011, OPMVX),
+    arith("vmulhu.vv", 0b100100, OPMVV),
+    arith("vmulhu.vx", 0b100100, OPMVX),
+    arith("vmul.vv", 0b100101, OPMVV),
+    arith("vmul.vx", 0b100101, OPMVX),
+    arith("vmulhsu.vv", 0b100110, OPMVV),
+    arith("vmulhsu.vx", 0b100110, OPMVX),
+    arith("vmulh.vv", 0b100111, OPMVV),
+    arith("vmulh.vx", 0b100111, OPMVX),
+    arith("vmadd.vv", 0b101001, OPMVV),
+    arith("vmadd.vx", 0b101001, OPMVX),
+    arith("vnmsub.vv", 0b101011, OPMVV),
+    arith("vnmsub.vx", 0b101011, OPMVX),
+    arith("vmacc.vv", 0b101101, OPMVV),
+    arith("vmacc.vx", 0b101101, OPMVX),
+    arith("vnmsac.vv", 0b101111, OPMVV),
+    arith("vnmsac.vx", 0b101111, OPMVX),
+    carry("vadc.vvm", 0b010000, OPIVV),
+    carry("vadc.vxm", 0b010000, OPIVX),
+    carry("vadc.vim", 0b010000, OPIVI),
+    carry("vsbc.vvm", 0b010010, OPIVV),
+    carry("vsbc.vxm", 0b010010, OPIVX),
+    carry("vmerge.vvm", 0b010111, OPIVV),
+    carry("vmerge.vxm", 0b010111, OPIVX),
+    carry("vmerge.vim", 0b010111, OPIVI),
+    opv("vmv.v.v", InsnClass::Arith, 0b010111, OPIVV, L, Operand::Fixed(0), L, Vm::Unmasked),
+    opv("vmv.v.x", InsnClass::Arith, 0b010111, OPIVX, L, Operand::Fixed(0), X, Vm::Unmasked),
+    opv("vmv.v.i", InsnClass::Arith, 0b010111, OPIVI, L, Operand::Fixed(0), Operand::Simm5, Vm::Unmasked),
+    extend("vzext.vf8", 0b00010, 8),
+    extend("vsext.vf8", 0b00011, 8),
+    extend("vzext.vf4", 0b00100, 4),
+    extend("vsext.vf4", 0b00101, 4),
+    extend("vzext.vf2", 0b00110, 2),
+    extend("vsext.vf2", 0b00111, 2),
+    reduce("vredsum.vs", 0b000000, OPMVV),
+    reduce("vredand.vs", 0b000001, OPMVV),
+    reduce("vredor.vs", 0b000010, OPMVV),
+    reduce("vredxor.vs", 0b000011, OPMVV),
+    reduce("vredminu.vs", 0b000100, OPMVV),
+    reduce("vredmin.vs", 0b000101, OPMVV),
+    reduce("vredmaxu.vs", 0b000110, OPMVV),
+    reduce("vredmax.vs", 0b000111, OPMVV),
+
+    widen("vwaddu.vv", 0b110000, OPMVV),
+    widen("vwaddu.vx", 0b110000, OPMVX),
+    widen("vwadd.vv", 0b110001, OPMVV),
+    widen("vwadd.vx", 0b110001, OPMVX),
+    widen("vwsubu.vv", 0b110010, OPMVV),
+    widen("vwsubu.vx", 0b110010, OPMVX),
+    widen("vwsub.vv", 0b110011, OPMVV),
+    widen("vwsub.vx", 0b110011, OPMVX),
+    widen_w("vwaddu.wv", 0b110100, OPMVV),
+    widen_w("vwaddu.wx", 0b110100, OPMVX),
+    widen_w("vwadd.wv", 0b110101, OPMVV),
+    widen_w("vwadd.wx", 0b110101, OPMVX),
+    widen_w("vwsubu.wv", 0b110110, OPMVV),
+    widen_w("vwsubu.wx", 0b110110, OPMVX),
+    widen_w("vwsub.wv", 0b110111, OPMVV),
+    widen_w("vwsub.wx", 0b110111, OPMVX),
+    widen("vwmulu.vv", 0b111000, OPMVV),
+    widen("vwmulu.vx", 0b111000, OPMVX),
+    widen("vwmulsu.vv", 0b111010, OPMVV),
+    widen("vwmulsu.vx", 0b111010, OPMVX),
+    widen("vwmul.vv", 0b111011, OPMVV),
+    widen("vwmul.vx", 0b111011, OPMVX),
+    widen("vwmaccu.vv", 0b111100, OPMVV),
+    widen("vwmaccu.vx", 0b111100, OPMVX),
+    widen("vwmacc.vv", 0b111101, OPMVV),
+    widen("vwmacc.vx", 0b111101, OPMVX),
+    widen("vwmaccus.vx", 0b111110, OPMVX),
+    widen("vwmaccsu.vv", 0b111111, OPMVV),
+    widen("vwmaccsu.vx", 0b111111, OPMVX),
+    reduce_w("vwredsumu.vs", 0b110000),
+    reduce_w("vwredsum.vs", 0b110001),
+
+    narrow("vnsrl.wv", 0b101100, OPIVV),
+    narrow("vnsrl.wx", 0b101100, OPIVX),
+    narrow("vnsrl.wi", 0b101100, OPIVI),
+    narrow("vnsra.wv", 0b101101, OPIVV),
+    narrow("vnsra.wx", 0b101101, OPIVX),
+    narrow("vnsra.wi", 0b101101, OPIVI),
+    narrow("vnclipu.wv", 0b101110, OPIVV),
+    narrow("vnclipu.wx", 0b101110, OPIVX),
+    narrow("vnclipu.wi", 0b101110, OPIVI),
+    narrow("vnclip.wv", 0b101111, OPIVV),
+    narrow("vnclip.wx", 0b101111, OPIVX),
+    narrow("vnclip.wi", 0b101111, OPIVI),
+
+    compare("vmseq.vv", 0b011000, OPIVV),
+    compare("vmseq.vx", 0b011000, OPIVX),
+    compare("vmseq.vi", 0b011000, OPIVI),
+    compare("vmsne.vv", 0b011001, OPIVV),
+    compare("vmsne.vx", 0b011001, OPIVX),
+    compare("vmsne.vi", 0b011001, OPIVI),
+    compare("vmsltu.vv", 0b011010, OPIVV),
+    compare("vmsltu.vx", 0b011010, OPIVX),
+    compare("vmslt.vv", 0b011011, OPIVV),
+    compare("vmslt.vx", 0b011011, OPIVX),
+    compare("vmsleu.vv", 0b011100, OPIVV),
+    compare("vmsleu.vx", 0b011100, OPIVX),
+    compare("vmsleu.vi", 0b011100, OPIVI),
+    compare("vmsle.vv", 0b011101, OPIVV),
+    compare("vmsle.vx", 0b011101, OPIVX),
+    compare("vmsle.vi", 0b011101, OPIVI),
+    compare("vmsgtu.vx", 0b011110, OPIVX),
+    compare("vmsgtu.vi", 0b011110, OPIVI),
+    compare("vmsgt.vx", 0b011111, OPIVX),
+    compare("vmsgt.vi", 0b011111, OPIVI),
+    carry_out("vmadc.vvm", 0b010001, OPIVV, Vm::Masked),
+    carry_out("vmadc.vxm", 0b010001, OPIVX, Vm::Masked),
+    carry_out("vmadc.vim", 0b010001, OPIVI, Vm::Masked),
+    carry_out("vmadc.vv", 0b010001, OPIVV, Vm::Unmasked),
+    carry_out("vmadc.vx", 0b010001, OPIVX, Vm::Unmasked),
+    carry_out("vmadc.vi", 0b010001, OPIVI, Vm::Unmasked),
+    carry_out("vmsbc.vvm", 0b010011, OPIVV, Vm::Masked),
+    carry_out("vmsbc.vxm", 0b010011, OPIVX, Vm::Masked),
+    carry_out("vmsbc.vv", 0b010011, OPIVV, Vm::Unmasked),
+    carry_out("vmsbc.vx", 0b010011, OPIVX, Vm::Unmasked),
+    mask_logical("vmandn.mm", 0b011000),
+    mask_logical("vmand.mm", 0b011001),
+    mask_logical("vmor.mm", 0b011010),
+    mask_logical("vmxor.mm", 0b011011),
+    mask_logical("vmorn.mm", 0b011100),
+    mask_logical("vmnand.mm", 0b011101),
+    mask_logical("vmnor.mm", 0b011110),
+    mask_logical("vmxnor.mm", 0b011111),
+    opv("vcpop.m", InsnClass::Mask, 0b010000, OPMVV, X, ONE, Operand::Fixed(0b10000), Vm::Free),
+    opv("vfirst.m", InsnClass::Mask, 0b010000, OPMVV, X, ONE, Operand::Fixed(0b10001), Vm::Free),
+    mask_unary("vmsbf.m", 0b00001),
+    mask_unary("vmsof.m", 0b00010),
+    mask_unary("vmsif.m", 0b00011),
+    {
+        let mut o = opv("viota.m", InsnClass::Mask, 0b010100, OPMVV, L, ONE, Operand::Fixed(0b10000), Vm::Free);
+        o.disjoint = true;
+        o
+    },
+    opv("vid.v", InsnClass::Mask, 0b010100, OPMVV, L, Operand::Fixed(0), Operand::Fixed(0b10001), Vm::Free),
+
+    opv("vmv.x.s", InsnClass::Permutation, 0b010000, OPMVV, X, ONE, Operand::Fixed(0), Vm::Unmasked),
+    opv("vmv.s.x", InsnClass::Permutation, 0b010000, OPMVX, ONE, Operand::Fixed(0), X, Vm::Unmasked),
+    permute("vslideup.vx", 0b001110, OPIVX, X),
+    permute("vslideup.vi", 0b001110, OPIVI, Operand::Uimm5),
+    permute("vslide1up.vx", 0b001110, OPMVX, X),
+    slide("vslidedown.vx", 0b001111, OPIVX),
+    {
+        let mut o = slide("vslidedown.vi", 0b001111, OPIVI);
+        o.vs1 = Operand::Uimm5;
+        o
+    },
+    slide("vslide1down.vx", 0b001111, OPMVX),
+    permute("vrgather.vv", 0b001100, OPIVV, L),
+    permute("vrgatherei16.vv", 0b001110, OPIVV, Operand::V(Emul::Eew(16))),
+    permute("vrgather.vx", 0b001100, OPIVX, X),
+    permute("vrgather.vi", 0b001100, OPIVI, Operand::Uimm5),
+    {
+        let mut o = permute("vcompress.vm", 0b010111, OPMVV, ONE);
+        o.vm = Vm::Unmasked;
+        o.bits |= 1 << 25;
+        o.mask |= 1 << 25;
+        o
+    },
+    whole_move("vmv1r.v", 1),
+    whole_move("vmv2r.v", 2),
+    whole_move("vmv4r.v", 4),
+    whole_move("vmv8r.v", 8),
+
+    mask_mem("vlm.v", false),
+    unit("vle8.v", false, 8),
+    unit("vle16.v", false, 16),
+    unit("vle32.v", false, 32),
+    unit("vle64.v", false, 64),
+    unit("vle128.v", false, 128),
+    unit("vle256.v", false, 256),
+    unit("vle512.v", false, 512),
+    unit("vle1024.v", false, 1024),
+    strided("vlse8.v", false, 8),
+    strided("vlse16.v", false, 16),
+    strided("vlse32.v", false, 32),
+    strided("vlse64.v", false, 64),
+    strided("vlse128.v", false, 128),
+    strided("vlse256.v", false, 256),
+    strided("vlse512.v", false, 512),
+    strided("vlse1024.v", false, 1024),
+    indexed("vluxei8.v", false, false, 8),
+    indexed("vluxei16.v", false, false, 16),
+    indexed("vluxei32.v", false, false, 32),
+    indexed("vluxei64.v", false, false, 64),
+    indexed("vloxei8.v", false, true, 8),
+    indexed("vloxei16.v", false, true, 16),
+    indexed("vloxei32.v", false, true, 32),
+    indexed("vloxei64.v", false, true, 64),
+    whole("vl1re8.v", false, 1, 8),
+    whole("vl1re16.v", false, 1, 16),
+    whole("vl1re32.v", false, 1, 32),
+    whole("vl1re64.v", false, 1, 64),
+    whole("vl2re8.v", false, 2, 8),
+    whole("vl2re16.v", false, 2, 16),
+    whole("vl2re32.v", false, 2, 32),
+    whole("vl2re64.v", false, 2, 64),
+    whole("vl4re8.v", false, 4, 8),
+    whole("vl4re16.v", false, 4, 16),
+    whole("vl4re32.v", false, 4, 32),
+    whole("vl4re64.v", false, 4, 64),
+    whole("vl8re8.v", false, 8, 8),
+    whole("vl8re16.v", false, 8, 16),
+    whole("vl8re32.v", false, 8, 32),
+    whole("vl8re64.v", false, 8, 64),
+
+    mask_mem("vsm.v", true),
+    unit("vse8.v", true, 8),
+    unit("vse16.v", true, 16),
+    unit("vse32.v", true, 32),
+    unit("vse64.v", true, 64),
+    unit("vse128.v", true, 128),
+    unit("vse256.v", true, 256),
+    unit("vse512.v", true, 512),
+    unit("vse1024.v", true, 1024),
+    strided("vsse8.v", true, 8),
+    strided("vsse16.v", true, 16),
+    strided("vsse32.v", true, 32),
+    strided("vsse64.v", true, 64),
+    strided("vsse128.v", true, 128),
+    strided("vsse256.v", true, 256),
+    strided("vsse512.v", true, 512),
+    strided("vsse1024.v", true, 1024),
+    indexed("vsuxei8.v", true, false, 8),
+    indexed("vsuxei16.v", true, false, 16),
+    indexed("vsuxei32.v", true, false, 32),
+    indexed("vsuxei64.v", true, false, 64),
+    indexed("vsoxei8.v", true, true, 8),
+    indexed("vsoxei16.v", true, true, 16),
+    indexed("vsoxei32.v", true, true, 32),
+    indexed("vsoxei64.v", true, true, 64),
+    whole("vs1r.v", true, 1, 8),
+    whole("vs2r.v", true, 2, 8),
+    whole("vs4r.v", true, 4, 8),
+    whole("vs8r.v", true, 8, 8),
+];
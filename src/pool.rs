@@ -0,0 +1,167 @@
+//! A pool of processors, to avoid constructing a new `processor_t` (and
+//! allocating its memory) for every fuzz input.
+//!
+//! Processors are reset with [`Spike::reset`] when they are returned, so every
+//! [`SpikePool::get`] hands out a processor indistinguishable from a new one.
+//! Those with commit logging enabled are dropped instead, a reset can't turn
+//! it off.
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{ConfigError, Coverage, Spike};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Processors constructed because the pool was empty.
+    pub created: u64,
+    /// Processors handed out again after a reset.
+    pub reused: u64,
+    pub resets: u64,
+    /// Total time spent in [`Spike::reset`].
+    pub reset_time: Duration,
+    /// Total bytes of memory cleared by resets.
+    pub bytes_cleared: u64,
+    /// Processors dropped on return because commit logging was enabled.
+    pub discarded: u64,
+}
+
+impl PoolStats {
+    pub fn mean_reset_time(&self) -> Duration {
+        if self.resets == 0 {
+            Duration::ZERO
+        } else {
+            self.reset_time / self.resets as u32
+        }
+    }
+}
+
+pub struct SpikePool {
+    vlen: u32,
+    elen: u32,
+    mem_size: u64,
+    idle: Mutex<Vec<Spike>>,
+    stats: Mutex<PoolStats>,
+    /// Coverage of processors returned to the pool, if enabled.
+    coverage: Mutex<Option<Coverage>>,
+}
+
+impl SpikePool {
+    /// An empty pool of processors created with `Spike::new(vlen, elen,
+    /// mem_size)`. `const` so the pool can be a `static` in fuzz targets.
+    pub const fn new(vlen: u32, elen: u32, mem_size: u64) -> Self {
+        Self {
+            vlen,
+            elen,
+            mem_size,
+            idle: Mutex::new(Vec::new()),
+            stats: Mutex::new(PoolStats {
+                created: 0,
+                reused: 0,
+                resets: 0,
+                reset_time: Duration::ZERO,
+                bytes_cleared: 0,
+                discarded: 0,
+            }),
+            coverage: Mutex::new(None),
+        }
+    }
+
+    /// Enable coverage on the processors handed out, and collect it into
+    /// [`coverage`](Self::coverage) when they are returned.
+    pub fn enable_coverage(&self) {
+        self.coverage
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Coverage::default);
+        for spike in self.idle.lock().unwrap().iter() {
+            spike.set_coverage(true);
+        }
+    }
+
+    /// The merged coverage of every processor returned since
+    /// [`enable_coverage`](Self::enable_coverage).
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.lock().unwrap().clone()
+    }
+
+    /// Take a processor in its initial state, constructing one if none is
+    /// idle. Fails if the pool's configuration is rejected by [`Spike::new`].
+    pub fn get(&self) -> Result<PooledSpike<'_>, ConfigError> {
+        let spike = self.idle.lock().unwrap().pop();
+        let mut stats = self.stats.lock().unwrap();
+        let spike = match spike {
+            Some(spike) => {
+                stats.reused += 1;
+                spike
+            }
+            None => {
+                let spike = Spike::new(self.vlen, self.elen, self.mem_size)?;
+                spike.set_coverage(self.coverage.lock().unwrap().is_some());
+                stats.created += 1;
+                spike
+            }
+        };
+        Ok(PooledSpike {
+            spike: Some(spike),
+            pool: self,
+        })
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// The number of idle processors.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    fn put(&self, spike: Spike) {
+        if let Some(coverage) = self.coverage.lock().unwrap().as_mut() {
+            coverage.merge(&spike.coverage());
+        }
+        if spike.commit_log_enabled() {
+            self.stats.lock().unwrap().discarded += 1;
+            return;
+        }
+        let start = Instant::now();
+        let cleared = spike.reset();
+        let elapsed = start.elapsed();
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.resets += 1;
+            stats.reset_time += elapsed;
+            stats.bytes_cleared += cleared;
+        }
+        self.idle.lock().unwrap().push(spike);
+    }
+}
+
+/// A processor borrowed from a [`SpikePool`], returned to it on drop.
+pub struct PooledSpike<'a> {
+    spike: Option<Spike>,
+    pool: &'a SpikePool,
+}
+
+impl<'a> Deref for PooledSpike<'a> {
+    type Target = Spike;
+
+    fn deref(&self) -> &Spike {
+        self.spike.as_ref().unwrap()
+    }
+}
+
+impl<'a> DerefMut for PooledSpike<'a> {
+    fn deref_mut(&mut self) -> &mut Spike {
+        self.spike.as_mut().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledSpike<'a> {
+    fn drop(&mut self) {
+        if let Some(spike) = self.spike.take() {
+            self.pool.put(spike);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! The interpreter behind the `spike-sys-repl` binary.
+//!
+//! Each line is either an assembler statement, executed right away, or one of
+//! the commands below. After a statement the x registers, vector registers,
+//! `vl` and `vtype` it changed are printed; memory isn't diffed, use `mem`.
+//!
+//! ```text
+//! vsetivli t0, 8, e64, m1
+//! x t0 = 4096
+//! v v10 e64 = 1, 2, 3
+//! mem 0x1000 64
+//! ```
+use std::fmt::Write;
+
+use crate::asm::{imm, xreg, ExecuteAsmError};
+use crate::insn::{VReg, XReg};
+use crate::vtype::VType;
+use crate::{Error, Spike};
+
+pub const HELP: &str = "\
+<statement>                 assemble and execute, e.g. `vadd.vv v2, v10, v20`
+x [<reg> [= <value>]]       show all x registers, or show or set one
+v <reg> [e<sew>]            show a vector register as elements of <sew> bits,
+                            the current SEW by default
+v <reg> [e<sew>] = <e0>, ...
+                            set elements from element 0, continuing into the
+                            following registers
+mem <addr> <len> [e<sew>]   show memory, as bytes or elements
+mem <addr> [e<sew>] = <e0>, ...
+                            store elements, bytes by default
+vtype                       show vl, vtype and vstart
+reset                       reset the processor
+help                        show this";
+
+/// Elements of up to this many bits are shown as numbers, wider ones as
+/// little-endian hex strings.
+const MAX_NUMERIC_SEW: u32 = 64;
+
+#[derive(Debug)]
+pub enum ReplError {
+    /// A command with missing or invalid arguments.
+    Usage(String),
+    Asm(ExecuteAsmError),
+    Spike(Error),
+}
+
+impl std::fmt::Display for ReplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplError::Usage(s) => f.write_str(s),
+            ReplError::Asm(e) => e.fmt(f),
+            ReplError::Spike(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReplError {}
+
+impl From<Error> for ReplError {
+    fn from(e: Error) -> Self {
+        ReplError::Spike(e)
+    }
+}
+
+fn usage<T>(s: impl Into<String>) -> Result<T, ReplError> {
+    Err(ReplError::Usage(s.into()))
+}
+
+/// What a statement may change, to show the difference.
+struct Snapshot {
+    x: Vec<u64>,
+    vl: u64,
+    vtype: u64,
+    v: Vec<u8>,
+}
+
+pub struct Repl {
+    spike: Spike,
+}
+
+impl Repl {
+    pub fn new(spike: Spike) -> Self {
+        Self { spike }
+    }
+
+    pub fn spike(&self) -> &Spike {
+        &self.spike
+    }
+
+    /// Evaluate one line, returning the text to print. Blank lines and
+    /// comments evaluate to an empty string.
+    pub fn eval(&mut self, line: &str) -> Result<String, ReplError> {
+        let line = match line.find('#') {
+            Some(n) => &line[..n],
+            None => line,
+        };
+        let (lhs, rhs) = match line.find('=') {
+            Some(n) => (&line[..n], Some(line[n + 1..].trim())),
+            None => (line, None),
+        };
+        let words: Vec<&str> = lhs.split_whitespace().collect();
+        match (words.first().copied(), rhs) {
+            (None, None) => Ok(String::new()),
+            (Some("x"), _) => self.xreg(&words[1..], rhs),
+            (Some("v"), _) => self.vreg(&words[1..], rhs),
+            (Some("mem"), _) => self.mem(&words[1..], rhs),
+            (Some("vtype"), None) if words.len() == 1 => self.vtype(),
+            (Some("reset"), None) if words.len() == 1 => {
+                self.spike.reset();
+                Ok(String::new())
+            }
+            (Some("help"), None) if words.len() == 1 => Ok(HELP.to_string()),
+            _ => self.execute(line),
+        }
+    }
+
+    fn execute(&self, stmt: &str) -> Result<String, ReplError> {
+        let before = self.snapshot()?;
+        self.spike.execute_asm(stmt).map_err(ReplError::Asm)?;
+        let after = self.snapshot()?;
+
+        let mut out = String::new();
+        for (i, (old, new)) in before.x.iter().zip(&after.x).enumerate() {
+            if old != new {
+                writeln!(out, "{} = {}", XReg::ALL[i], hex_dec(*new)).unwrap();
+            }
+        }
+        if before.vl != after.vl || before.vtype != after.vtype {
+            writeln!(out, "{}", self.vtype()?).unwrap();
+        }
+        let vlenb = self.spike.get_vlen() as usize / 8;
+        let sew = self.sew();
+        for (i, (old, new)) in before
+            .v
+            .chunks(vlenb)
+            .zip(after.v.chunks(vlenb))
+            .enumerate()
+        {
+            if old != new {
+                writeln!(out, "{}", show_vreg(VReg::ALL[i], new, sew)).unwrap();
+            }
+        }
+        Ok(out.trim_end().to_string())
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, Error> {
+        let x = (0..32)
+            .map(|i| self.spike.get_xreg(i))
+            .collect::<Result<_, _>>()?;
+        let mut v = vec![0; 32 * self.spike.get_vlen() as usize / 8];
+        self.spike.get_vreg(0, &mut v)?;
+        Ok(Snapshot {
+            x,
+            vl: self.spike.get_vl(),
+            vtype: self.spike.get_vtype(),
+            v,
+        })
+    }
+
+    /// The current SEW, or 8 if `vill` is set.
+    fn sew(&self) -> u32 {
+        VType::from_bits(self.spike.get_vtype()).map_or(8, |vtype| vtype.sew)
+    }
+
+    fn xreg(&self, args: &[&str], rhs: Option<&str>) -> Result<String, ReplError> {
+        let reg = match args {
+            [] if rhs.is_none() => {
+                let mut out = String::new();
+                for (i, reg) in XReg::ALL.iter().enumerate() {
+                    let x = self.spike.get_xreg(i as u64)?;
+                    let sep = if i % 4 == 3 { "\n" } else { "  " };
+                    write!(out, "{:>4} = 0x{:016x}{}", reg.to_string(), x, sep).unwrap();
+                }
+                return Ok(out.trim_end().to_string());
+            }
+            [reg] => xreg(reg).map_err(|e| ReplError::Usage(e.to_string()))?,
+            _ => return usage("usage: x [<reg> [= <value>]]"),
+        };
+        if let Some(value) = rhs {
+            let value = imm(value).map_err(|e| ReplError::Usage(e.to_string()))?;
+            self.spike.set_xreg(reg as u64, value as u64)?;
+        }
+        let x = self.spike.get_xreg(reg as u64)?;
+        Ok(format!("{} = {}", XReg::ALL[reg as usize], hex_dec(x)))
+    }
+
+    fn vreg(&self, args: &[&str], rhs: Option<&str>) -> Result<String, ReplError> {
+        let (reg, sew) = match args {
+            [reg] => (vreg(reg)?, self.sew()),
+            [reg, sew] => (vreg(reg)?, parse_sew(sew)?),
+            _ => return usage("usage: v <reg> [e<sew>] [= <e0>, ...]"),
+        };
+        let vlenb = self.spike.get_vlen() / 8;
+        if let Some(values) = rhs {
+            let bytes = elements_to_bytes(values, sew)?;
+            self.spike.set_vreg(reg.index() as u64 * vlenb, &bytes)?;
+        }
+        let mut buf = vec![0; vlenb as usize];
+        self.spike.get_vreg(reg.index() as u64 * vlenb, &mut buf)?;
+        Ok(show_vreg(reg, &buf, sew))
+    }
+
+    fn mem(&self, args: &[&str], rhs: Option<&str>) -> Result<String, ReplError> {
+        let addr = match args.first() {
+            Some(addr) => imm(addr).map_err(|e| ReplError::Usage(e.to_string()))? as u64,
+            None => return usage("usage: mem <addr> <len> [e<sew>] | mem <addr> [e<sew>] = ..."),
+        };
+        if let Some(values) = rhs {
+            let sew = match args {
+                [_] => 8,
+                [_, sew] => parse_sew(sew)?,
+                _ => return usage("usage: mem <addr> [e<sew>] = <e0>, ..."),
+            };
+            let bytes = elements_to_bytes(values, sew)?;
+            self.spike.store_mem(addr, &bytes)?;
+            return Ok(String::new());
+        }
+        let (len, sew) = match args {
+            [_, len] => (len, None),
+            [_, len, sew] => (len, Some(parse_sew(sew)?)),
+            _ => return usage("usage: mem <addr> <len> [e<sew>]"),
+        };
+        let len = imm(len).map_err(|e| ReplError::Usage(e.to_string()))?;
+        if len < 0 || len as u64 > self.spike.get_mem_size() {
+            return usage(format!(
+                "length {} is outside the {} bytes of memory",
+                len,
+                self.spike.get_mem_size()
+            ));
+        }
+        let mut buf = vec![0; len as usize];
+        self.spike.load_mem(addr, &mut buf)?;
+
+        let mut out = String::new();
+        match sew {
+            None => {
+                for (i, line) in buf.chunks(16).enumerate() {
+                    write!(out, "0x{:08x}:", addr + 16 * i as u64).unwrap();
+                    for b in line {
+                        write!(out, " {:02x}", b).unwrap();
+                    }
+                    writeln!(out).unwrap();
+                }
+            }
+            Some(sew) => {
+                let elements = elements(&buf, sew);
+                let per_line = std::cmp::max(1, 128 / sew as usize);
+                for (i, line) in elements.chunks(per_line).enumerate() {
+                    let line_addr = addr + (i * per_line) as u64 * sew as u64 / 8;
+                    writeln!(out, "0x{:08x}: {}", line_addr, line.join(" ")).unwrap();
+                }
+            }
+        }
+        Ok(out.trim_end().to_string())
+    }
+
+    fn vtype(&self) -> Result<String, ReplError> {
+        let vtype = self.spike.get_vtype();
+        let text = match VType::from_bits(vtype) {
+            Some(vtype) => vtype.to_string(),
+            None => "vill".to_string(),
+        };
+        let vstart = self.spike.get_csr(0x008)?;
+        Ok(format!(
+            "vl = {}, vtype = {} (0x{:x}), vstart = {}",
+            self.spike.get_vl(),
+            text,
+            vtype,
+            vstart
+        ))
+    }
+}
+
+fn hex_dec(x: u64) -> String {
+    format!("0x{:x} ({})", x, x as i64)
+}
+
+fn vreg(s: &str) -> Result<VReg, ReplError> {
+    match s.strip_prefix('v').and_then(|n| n.parse().ok()) {
+        Some(n) => {
+            VReg::new(n).ok_or_else(|| ReplError::Usage(format!("invalid register `{}`", s)))
+        }
+        None => usage(format!("invalid register `{}`", s)),
+    }
+}
+
+fn parse_sew(s: &str) -> Result<u32, ReplError> {
+    match s.strip_prefix('e').and_then(|n| n.parse::<u32>().ok()) {
+        Some(sew) if sew.is_power_of_two() && (8..=1024).contains(&sew) => Ok(sew),
+        _ => usage(format!(
+            "invalid element width `{}`, expected e8 to e1024",
+            s
+        )),
+    }
+}
+
+/// Split `bytes` into elements of `sew` bits.
+fn elements(bytes: &[u8], sew: u32) -> Vec<String> {
+    bytes
+        .chunks(sew as usize / 8)
+        .map(|e| {
+            if sew <= MAX_NUMERIC_SEW {
+                let mut buf = [0u8; 8];
+                buf[..e.len()].copy_from_slice(e);
+                format!("0x{:x}", u64::from_le_bytes(buf))
+            } else {
+                let hex: Vec<String> = e.iter().rev().map(|b| format!("{:02x}", b)).collect();
+                format!("0x{}", hex.concat())
+            }
+        })
+        .collect()
+}
+
+fn show_vreg(reg: VReg, bytes: &[u8], sew: u32) -> String {
+    format!("{} e{} = [{}]", reg, sew, elements(bytes, sew).join(", "))
+}
+
+fn elements_to_bytes(values: &str, sew: u32) -> Result<Vec<u8>, ReplError> {
+    if sew > MAX_NUMERIC_SEW {
+        return usage(format!(
+            "can't set elements wider than {} bits",
+            MAX_NUMERIC_SEW
+        ));
+    }
+    let mut bytes = vec![];
+    for value in values.split(',').map(str::trim) {
+        let value = imm(value).map_err(|e| ReplError::Usage(e.to_string()))?;
+        bytes.extend_from_slice(&value.to_le_bytes()[..sew as usize / 8]);
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,194 @@
+//! Round-trip verification of the disassembler against rvv-encode.
+//!
+//! An instruction round-trips if Spike's disassembly of it encodes back to the
+//! same bits. Mismatches are classified by comparing the decoded operands of
+//! both encodings.
+use std::fmt;
+
+use crate::decode::{decode, DecodedInsn, Operand};
+use crate::disasm::Disassembler;
+use crate::Error;
+
+thread_local! {
+    static DISASSEMBLER: Result<Disassembler, Error> = Disassembler::new(64);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoundTripErrorKind {
+    /// Spike doesn't know the encoding.
+    Undecodable,
+    /// rvv-encode doesn't know the mnemonic.
+    UnknownMnemonic,
+    /// rvv-encode rejected the text.
+    Rejected(String),
+    /// The same registers, in a different order.
+    OperandOrder,
+    /// An immediate (including vtype) didn't survive the round trip.
+    ImmediateRange,
+    /// Masked encoded as unmasked or vice versa.
+    Masked,
+    /// A different instruction or different registers.
+    Mismatch,
+}
+
+impl fmt::Display for RoundTripErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundTripErrorKind::Undecodable => write!(f, "undecodable"),
+            RoundTripErrorKind::UnknownMnemonic => write!(f, "unknown mnemonic"),
+            RoundTripErrorKind::Rejected(e) => write!(f, "rejected: {}", e),
+            RoundTripErrorKind::OperandOrder => write!(f, "operand order"),
+            RoundTripErrorKind::ImmediateRange => write!(f, "immediate out of range"),
+            RoundTripErrorKind::Masked => write!(f, "masked vs unmasked"),
+            RoundTripErrorKind::Mismatch => write!(f, "mismatch"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundTripError {
+    pub insn: u32,
+    /// Spike's disassembly of `insn`, empty if undecodable.
+    pub text: String,
+    /// What rvv-encode produced, if anything.
+    pub encoded: Option<u32>,
+    pub kind: RoundTripErrorKind,
+}
+
+impl fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08x} `{}`: {}", self.insn, self.text, self.kind)?;
+        if let Some(encoded) = self.encoded {
+            write!(f, " (encoded as 0x{:08x})", encoded)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RoundTripError {}
+
+/// Check that `insn` survives disassembly followed by encoding.
+pub fn check(insn: u32) -> Result<(), RoundTripError> {
+    let error = |text: &str, encoded, kind| RoundTripError {
+        insn,
+        text: text.to_string(),
+        encoded,
+        kind,
+    };
+    let decoded = decode(insn).ok_or_else(|| error("", None, RoundTripErrorKind::Undecodable))?;
+    let text = DISASSEMBLER
+        .with(|dis| match dis {
+            Ok(dis) => dis.disassemble(insn as u64).ok(),
+            Err(_) => None,
+        })
+        .ok_or_else(|| error("", None, RoundTripErrorKind::Undecodable))?;
+    if text.contains("unknown") {
+        return Err(error(&text, None, RoundTripErrorKind::Undecodable));
+    }
+    let encoded = match rvv_encode::encode(&text, false) {
+        Ok(Some(encoded)) => encoded,
+        Ok(None) => return Err(error(&text, None, RoundTripErrorKind::UnknownMnemonic)),
+        Err(e) => return Err(error(&text, None, RoundTripErrorKind::Rejected(e))),
+    };
+    if encoded == insn {
+        return Ok(());
+    }
+    let kind = match decode(encoded) {
+        Some(other) => classify(&decoded, &other),
+        None => RoundTripErrorKind::Mismatch,
+    };
+    Err(error(&text, Some(encoded), kind))
+}
+
+fn classify(a: &DecodedInsn, b: &DecodedInsn) -> RoundTripErrorKind {
+    if a.mnemonic != b.mnemonic {
+        return RoundTripErrorKind::Mismatch;
+    }
+    let without_vm = |insn: &DecodedInsn| -> Vec<Operand> {
+        insn.operands
+            .iter()
+            .filter(|o| !matches!(o, Operand::Vm(_)))
+            .cloned()
+            .collect()
+    };
+    if a.masked() != b.masked() && without_vm(a) == without_vm(b) {
+        return RoundTripErrorKind::Masked;
+    }
+    let immediates = |insn: &DecodedInsn| -> Vec<i64> {
+        insn.operands
+            .iter()
+            .filter_map(|o| match o {
+                Operand::Imm(imm) => Some(*imm),
+                _ => None,
+            })
+            .collect()
+    };
+    if immediates(a) != immediates(b) {
+        return RoundTripErrorKind::ImmediateRange;
+    }
+    let mut ra = registers(a);
+    let mut rb = registers(b);
+    if ra != rb {
+        ra.sort_unstable();
+        rb.sort_unstable();
+        if ra == rb {
+            return RoundTripErrorKind::OperandOrder;
+        }
+    }
+    RoundTripErrorKind::Mismatch
+}
+
+/// Register numbers in operand order.
+fn registers(insn: &DecodedInsn) -> Vec<u32> {
+    insn.operands
+        .iter()
+        .filter_map(|o| match o {
+            Operand::Vd(r) | Operand::Vs3(r) | Operand::Vs2(r) | Operand::Vs1(r) => Some(r.index()),
+            Operand::Rd(r) | Operand::Rs1(r) | Operand::Rs2(r) => Some(r.index()),
+            Operand::Fd(r) | Operand::Fs1(r) => Some(r.index()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The result of a [`sweep`].
+#[derive(Clone, Debug, Default)]
+pub struct Sweep {
+    /// Encodings that round-tripped.
+    pub passed: usize,
+    /// Encodings Spike doesn't know.
+    pub undecodable: usize,
+    pub errors: Vec<RoundTripError>,
+}
+
+/// Check every encoding with the given major opcode, and optionally funct3
+/// (bits 14:12) and funct6 (bits 31:26, which hold nf, mew and mop for loads
+/// and stores). All other bits are enumerated, so leaving both open means
+/// 2^25 encodings.
+pub fn sweep(opcode: u32, funct3: Option<u32>, funct6: Option<u32>) -> Sweep {
+    let mut fixed = opcode & 0x7f;
+    let mut mask = 0x7f;
+    if let Some(funct3) = funct3 {
+        fixed |= (funct3 & 0b111) << 12;
+        mask |= 0b111 << 12;
+    }
+    if let Some(funct6) = funct6 {
+        fixed |= (funct6 & 0b111111) << 26;
+        mask |= 0b111111 << 26;
+    }
+    let free: Vec<u32> = (7..32).filter(|bit| mask >> bit & 1 == 0).collect();
+
+    let mut r = Sweep::default();
+    for n in 0..1u64 << free.len() {
+        let insn = free
+            .iter()
+            .enumerate()
+            .fold(fixed, |insn, (i, bit)| insn | ((n >> i & 1) as u32) << bit);
+        match check(insn) {
+            Ok(()) => r.passed += 1,
+            Err(e) if e.kind == RoundTripErrorKind::Undecodable => r.undecodable += 1,
+            Err(e) => r.errors.push(e),
+        }
+    }
+    r
+}
@@ -0,0 +1,309 @@
+//! Running programs from memory, with breakpoints, watchpoints and
+//! conditional stops.
+//!
+//! A program is stored with [`Spike::store_mem`] and run from
+//! [`Spike::pc`], which is [`START_MEM`](crate::config::START_MEM) after a
+//! reset. [`Spike::run`] executes until something stops it; traps aren't
+//! taken, so an `ecall` at the end of the program stops with
+//! [`StopReason::Trap`].
+//!
+//! [`Spike::load_elf`] stores a statically linked RV64 executable instead. Its
+//! segments must fit in memory, so link it above `START_MEM`, e.g. with
+//! `-Ttext=0x10000`, rather than at the usual `0x80000000`.
+use std::ops::Range;
+
+use goblin::elf::header::EM_RISCV;
+use goblin::elf::program_header::{ProgramHeader, PT_LOAD};
+use goblin::elf::Elf;
+
+use crate::ffi::*;
+use crate::insn::{VReg, XReg};
+use crate::{Error, Spike};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn bits(self) -> i32 {
+        match self {
+            Access::Read => RVV_ACCESS_READ as i32,
+            Access::Write => RVV_ACCESS_WRITE as i32,
+            Access::ReadWrite => (RVV_ACCESS_READ | RVV_ACCESS_WRITE) as i32,
+        }
+    }
+}
+
+/// A condition checked after every instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    VlChanged,
+    VtypeChanged,
+    /// Written, even if with the same value. Register write conditions enable
+    /// commit logging, which slows execution down, see
+    /// [`Spike::enable_commit_log`].
+    XregWritten(XReg),
+    /// Any part of the register written, e.g. v9 by an LMUL=2 write to v8.
+    VregWritten(VReg),
+}
+
+impl Condition {
+    fn bits(self) -> (i32, u64) {
+        match self {
+            Condition::VlChanged => (RVV_COND_VL_CHANGED as i32, 0),
+            Condition::VtypeChanged => (RVV_COND_VTYPE_CHANGED as i32, 0),
+            Condition::XregWritten(r) => (RVV_COND_XREG_WRITTEN as i32, r.index() as u64),
+            Condition::VregWritten(r) => (RVV_COND_VREG_WRITTEN as i32, r.index() as u64),
+        }
+    }
+
+    fn from_bits(condition: i32, arg: u64) -> Option<Condition> {
+        match condition as u32 {
+            RVV_COND_VL_CHANGED => Some(Condition::VlChanged),
+            RVV_COND_VTYPE_CHANGED => Some(Condition::VtypeChanged),
+            RVV_COND_XREG_WRITTEN => XReg::new(arg as u32).map(Condition::XregWritten),
+            RVV_COND_VREG_WRITTEN => VReg::new(arg as u32).map(Condition::VregWritten),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StopReason {
+    /// `pc` is at a breakpoint, the instruction there hasn't executed.
+    Breakpoint { pc: u64 },
+    /// The instruction at `pc` accessed a watched range. `access` is either
+    /// `Read` or `Write`, and `addr` and `len` are those of the access.
+    Watchpoint {
+        pc: u64,
+        addr: u64,
+        len: u64,
+        access: Access,
+    },
+    /// The instruction at `pc` met `condition`.
+    Condition { pc: u64, condition: Condition },
+    /// The instruction at `pc` trapped, or couldn't be fetched.
+    Trap { pc: u64, error: Error },
+    /// The instruction limit was reached, `pc` is the next instruction.
+    Limit { pc: u64 },
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Parse(goblin::error::Error),
+    /// Not a 64-bit RISC-V ELF file.
+    NotRv64,
+    /// A segment doesn't fit in memory.
+    Segment {
+        addr: u64,
+        len: u64,
+        error: Error,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Parse(e) => write!(f, "invalid ELF: {}", e),
+            LoadError::NotRv64 => f.write_str("not a 64-bit RISC-V ELF file"),
+            LoadError::Segment { addr, len, error } => write!(
+                f,
+                "segment 0x{:x}..0x{:x} doesn't fit in memory: {}",
+                addr,
+                addr.saturating_add(*len),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<goblin::error::Error> for LoadError {
+    fn from(e: goblin::error::Error) -> Self {
+        LoadError::Parse(e)
+    }
+}
+
+fn malformed(message: &str) -> LoadError {
+    LoadError::Parse(goblin::error::Error::Malformed(message.to_string()))
+}
+
+/// The end of a loadable segment, checking that its file contents fit in it
+/// and that it doesn't wrap around the address space.
+fn segment_end(ph: &ProgramHeader) -> Result<u64, LoadError> {
+    if ph.p_filesz > ph.p_memsz {
+        return Err(malformed("segment larger in the file than in memory"));
+    }
+    ph.p_paddr
+        .checked_add(ph.p_memsz)
+        .ok_or_else(|| malformed("segment past the end of the address space"))
+}
+
+/// The range of addresses the loadable segments of `elf` occupy, to size
+/// memory before creating a processor. Empty if there are none.
+pub fn elf_extent(elf: &[u8]) -> Result<Range<u64>, LoadError> {
+    let parsed = Elf::parse(elf)?;
+    let mut extent: Option<Range<u64>> = None;
+    for ph in parsed
+        .program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD)
+    {
+        let end = segment_end(ph)?;
+        extent = Some(match extent {
+            Some(e) => e.start.min(ph.p_paddr)..e.end.max(end),
+            None => ph.p_paddr..end,
+        });
+    }
+    Ok(extent.unwrap_or(0..0))
+}
+
+impl Spike {
+    /// Store the loadable segments of `elf` at their physical addresses,
+    /// zeroing the rest of each segment, and set [`pc`](Self::pc) to the
+    /// entry point, which is returned.
+    pub fn load_elf(&self, elf: &[u8]) -> Result<u64, LoadError> {
+        let parsed = Elf::parse(elf)?;
+        if !parsed.is_64 || parsed.header.e_machine != EM_RISCV {
+            return Err(LoadError::NotRv64);
+        }
+        for ph in parsed
+            .program_headers
+            .iter()
+            .filter(|ph| ph.p_type == PT_LOAD)
+        {
+            segment_end(ph)?;
+            // Not ph.file_range(), which may overflow.
+            let offset = ph.p_offset as usize;
+            let data = offset
+                .checked_add(ph.p_filesz as usize)
+                .and_then(|end| elf.get(offset..end))
+                .ok_or_else(|| malformed("segment outside the file"))?;
+            // Checked before allocating, a bogus p_memsz mustn't abort.
+            if ph.p_memsz > self.get_mem_size() {
+                return Err(LoadError::Segment {
+                    addr: ph.p_paddr,
+                    len: ph.p_memsz,
+                    error: Error::from_code(-2),
+                });
+            }
+            let mut bytes = data.to_vec();
+            bytes.resize(ph.p_memsz as usize, 0);
+            self.store_mem(ph.p_paddr, &bytes)
+                .map_err(|error| LoadError::Segment {
+                    addr: ph.p_paddr,
+                    len: ph.p_memsz,
+                    error,
+                })?;
+        }
+        self.set_pc(parsed.entry);
+        Ok(parsed.entry)
+    }
+
+    pub fn pc(&self) -> u64 {
+        unsafe { rvv_get_pc(self.addr) }
+    }
+
+    pub fn set_pc(&self, pc: u64) {
+        unsafe { rvv_set_pc(self.addr, pc) }
+    }
+
+    pub fn add_breakpoint(&self, pc: u64) {
+        unsafe { rvv_add_breakpoint(self.addr, pc) }
+    }
+
+    /// Returns whether there was a breakpoint at `pc`.
+    pub fn remove_breakpoint(&self, pc: u64) -> bool {
+        unsafe { rvv_remove_breakpoint(self.addr, pc) == 0 }
+    }
+
+    /// Stop after an instruction accesses `range`. Fails if the processor
+    /// has no memory.
+    pub fn add_watchpoint(&self, range: Range<u64>, access: Access) -> Result<(), Error> {
+        let r = unsafe { rvv_add_watchpoint(self.addr, range.start, range.end, access.bits()) };
+        if r != 0 {
+            Err(Error::from_code(r))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether there was a watchpoint with the same range and access.
+    pub fn remove_watchpoint(&self, range: Range<u64>, access: Access) -> bool {
+        unsafe { rvv_remove_watchpoint(self.addr, range.start, range.end, access.bits()) == 0 }
+    }
+
+    pub fn add_condition(&self, condition: Condition) {
+        let (condition, arg) = condition.bits();
+        unsafe { rvv_add_condition(self.addr, condition, arg) }
+    }
+
+    pub fn remove_condition(&self, condition: Condition) -> bool {
+        let (condition, arg) = condition.bits();
+        unsafe { rvv_remove_condition(self.addr, condition, arg) == 0 }
+    }
+
+    /// Run from [`pc`](Self::pc) until a breakpoint, watchpoint, condition or
+    /// trap stops execution. A breakpoint at the starting pc is skipped, so
+    /// that calling `run` again continues after a breakpoint.
+    pub fn run(&self) -> StopReason {
+        self.run_for(u64::MAX)
+    }
+
+    /// Like [`run`](Self::run), executing at most `max_insns` instructions.
+    pub fn run_for(&self, max_insns: u64) -> StopReason {
+        let mut stop = rvv_stop_t::default();
+        let r = unsafe { rvv_run(self.addr, max_insns, &mut stop) };
+        let pc = stop.pc;
+        if r != 0 {
+            return StopReason::Trap {
+                pc,
+                error: Error::from_code(r),
+            };
+        }
+        match stop.reason as u32 {
+            RVV_STOP_BREAKPOINT => StopReason::Breakpoint { pc },
+            RVV_STOP_WATCHPOINT => StopReason::Watchpoint {
+                pc,
+                addr: stop.addr,
+                len: stop.len,
+                access: if stop.access as u32 == RVV_ACCESS_READ {
+                    Access::Read
+                } else {
+                    Access::Write
+                },
+            },
+            RVV_STOP_CONDITION => match Condition::from_bits(stop.condition, stop.arg) {
+                Some(condition) => StopReason::Condition { pc, condition },
+                // Only conditions added through Condition::bits are reported,
+                // anything else means the shim and this crate disagree.
+                None => StopReason::Trap {
+                    pc,
+                    error: Error {
+                        code: RVV_ERROR_EXCEPTION,
+                        message: Some(format!(
+                            "unknown condition {} with argument {}",
+                            stop.condition, stop.arg
+                        )),
+                    },
+                },
+            },
+            RVV_STOP_TRAP => StopReason::Trap {
+                pc,
+                error: Error::from_code(stop.error),
+            },
+            _ => StopReason::Limit { pc },
+        }
+    }
+
+    /// Execute the instruction at [`pc`](Self::pc). Returns
+    /// [`StopReason::Limit`] unless it trapped or hit a watchpoint or
+    /// condition.
+    pub fn step(&self) -> StopReason {
+        self.run_for(1)
+    }
+}
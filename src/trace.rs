@@ -0,0 +1,567 @@
+//! Per-instruction traces in the format of Spike's commit log
+//! (`spike --log-commits`), so that they can be compared with those of Spike
+//! itself and of other simulators.
+//!
+//! ```text
+//! core   0: 3 0x0000000000001000 (0x00000513) x10 0x0000000000000000
+//! core   0: 3 0x0000000000001004 (0x00a5b023) mem 0x0000000000002000 0x0000000000000000
+//! ```
+//!
+//! Spike also prints the vector configuration, e.g. `e64 m1 l2`, before
+//! vector register writes; it's left out here and skipped by [`parse`].
+use crate::disasm::insn_length;
+use crate::ffi::*;
+use crate::insn::{csrs, FReg, VReg, XReg};
+use crate::run::StopReason;
+use crate::{Model, Spike};
+
+/// The CSR numbers of `vl` and `vtype`.
+const VL: u32 = 0xc20;
+const VTYPE: u32 = 0xc21;
+
+/// The privilege level printed for every instruction, processors stay in
+/// machine mode.
+const PRIV: u32 = 3;
+
+/// CSRs Spike writes as a side effect of instructions, besides those the
+/// assembler knows.
+const SIDE_EFFECT_CSRS: &[(&str, u32)] = &[("sstatus", 0x100), ("mstatus", 0x300)];
+
+/// A register write or memory access by an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Xreg {
+        reg: XReg,
+        value: u64,
+    },
+    /// The low 64 bits of the register.
+    Freg {
+        reg: FReg,
+        value: u64,
+    },
+    /// The whole register after the instruction, little endian.
+    Vreg {
+        reg: VReg,
+        value: Vec<u8>,
+    },
+    Csr {
+        csr: u32,
+        value: u64,
+    },
+    /// `size` is 0 if unknown, Spike's commit log doesn't show it.
+    Load {
+        addr: u64,
+        size: u64,
+    },
+    Store {
+        addr: u64,
+        value: u64,
+        size: u64,
+    },
+}
+
+impl std::fmt::Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Xreg { reg, value } => write!(f, "x{:<2} 0x{:016x}", reg.index(), value),
+            Effect::Freg { reg, value } => write!(f, "f{:<2} 0x{:016x}", reg.index(), value),
+            Effect::Vreg { reg, value } => {
+                write!(f, "v{:<2} 0x", reg.index())?;
+                value.iter().rev().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Effect::Csr { csr, value } => {
+                write!(f, "c{}_{} 0x{:016x}", csr, csr_name(*csr), value)
+            }
+            Effect::Load { addr, .. } => write!(f, "mem 0x{:016x}", addr),
+            Effect::Store { addr, value, size } => write!(
+                f,
+                "mem 0x{:016x} 0x{:0width$x}",
+                addr,
+                value,
+                width = *size as usize * 2
+            ),
+        }
+    }
+}
+
+/// Spike's name for a CSR, `unknown` for those the assembler doesn't know.
+pub fn csr_name(csr: u32) -> &'static str {
+    csrs()
+        .chain(SIDE_EFFECT_CSRS)
+        .find(|(_, n)| *n == csr)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// A retired instruction and its effects, one line of a commit log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub pc: u64,
+    /// The encoding, 2 to 8 bytes as given by [`insn_length`].
+    pub insn: u64,
+    /// Register writes first, then loads, then stores.
+    pub effects: Vec<Effect>,
+}
+
+impl std::fmt::Display for Commit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let len = insn_length(self.insn as u16);
+        write!(
+            f,
+            "core   0: {} 0x{:016x} (0x{:0width$x})",
+            PRIV,
+            self.pc,
+            self.insn,
+            width = len * 2
+        )?;
+        for effect in &self.effects {
+            write!(f, " {}", effect)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sort key for register writes, which Spike records in no particular order.
+fn order(effect: &Effect) -> (u32, u64) {
+    match effect {
+        Effect::Xreg { reg, .. } => (0, reg.index() as u64),
+        Effect::Freg { reg, .. } => (1, reg.index() as u64),
+        Effect::Vreg { reg, .. } => (2, reg.index() as u64),
+        Effect::Csr { csr, .. } => (3, *csr as u64),
+        _ => (4, 0),
+    }
+}
+
+impl Spike {
+    /// Record register writes and memory accesses for [`effects`]. Slows
+    /// execution down and stays enabled until the processor is dropped, even
+    /// across resets, so [`SpikePool`](crate::SpikePool) doesn't reuse it.
+    ///
+    /// [`effects`]: Self::effects
+    pub fn enable_commit_log(&self) {
+        unsafe { rvv_enable_commit_log(self.addr) }
+    }
+
+    /// Whether commit logging is enabled, by
+    /// [`enable_commit_log`](Self::enable_commit_log) or by a register write
+    /// [`Condition`](crate::run::Condition).
+    pub fn commit_log_enabled(&self) -> bool {
+        unsafe { rvv_commit_log_enabled(self.addr) != 0 }
+    }
+
+    /// The effects of the last instruction executed, empty unless
+    /// [`enable_commit_log`](Self::enable_commit_log) was called.
+    pub fn effects(&self) -> Vec<Effect> {
+        let n = unsafe { rvv_get_effects(self.addr, std::ptr::null_mut(), 0) } as usize;
+        let mut raw = vec![rvv_effect_t::default(); n];
+        unsafe { rvv_get_effects(self.addr, raw.as_mut_ptr(), n as u64) };
+
+        let vlenb = self.get_vlen() / 8;
+        let mut r = vec![];
+        for e in raw {
+            let effect = match e.kind as u32 {
+                RVV_EFFECT_XREG => XReg::new(e.reg as u32).map(|reg| Effect::Xreg {
+                    reg,
+                    value: e.value,
+                }),
+                RVV_EFFECT_FREG => FReg::new(e.reg as u32).map(|reg| Effect::Freg {
+                    reg,
+                    value: e.value,
+                }),
+                RVV_EFFECT_VREG => VReg::new(e.reg as u32).map(|reg| {
+                    let mut value = vec![0; vlenb as usize];
+                    self.get_vreg(e.reg * vlenb, &mut value).unwrap();
+                    Effect::Vreg { reg, value }
+                }),
+                RVV_EFFECT_CSR => Some(Effect::Csr {
+                    csr: e.reg as u32,
+                    value: e.value,
+                }),
+                RVV_EFFECT_LOAD => Some(Effect::Load {
+                    addr: e.addr,
+                    size: e.size,
+                }),
+                RVV_EFFECT_STORE => Some(Effect::Store {
+                    addr: e.addr,
+                    value: e.value,
+                    size: e.size,
+                }),
+                _ => None,
+            };
+            r.extend(effect);
+        }
+        // Stable, so loads and stores keep their order.
+        r.sort_by_key(order);
+        r
+    }
+
+    /// Like [`step`](Self::step), also returning the instruction's commit
+    /// log entry unless it trapped. Commit logging must be enabled for the
+    /// entry to have effects.
+    pub fn step_traced(&self) -> (StopReason, Option<Commit>) {
+        let pc = self.pc();
+        let insn = self.fetch(pc);
+        let stop = self.step();
+        let commit = match (&stop, insn) {
+            (StopReason::Trap { .. }, _) | (_, None) => None,
+            (_, Some(insn)) => Some(Commit {
+                pc,
+                insn,
+                effects: self.effects(),
+            }),
+        };
+        (stop, commit)
+    }
+
+    /// The instruction at `pc`, if it's in memory.
+    fn fetch(&self, pc: u64) -> Option<u64> {
+        let mut bytes = [0u8; 8];
+        self.load_mem(pc, &mut bytes[..2]).ok()?;
+        let len = insn_length(u16::from_le_bytes([bytes[0], bytes[1]]));
+        self.load_mem(pc, &mut bytes[..len]).ok()?;
+        Some(u64::from_le_bytes(bytes))
+    }
+}
+
+/// Execute `insn` on any model and record the registers it changed as a
+/// commit at `pc`, which is only a label since models execute instructions
+/// outside of memory.
+///
+/// Unlike Spike's commit log, writes that leave a register unchanged and
+/// memory accesses aren't seen, so only compare logs recorded the same way.
+pub fn execute_traced<M: Model>(model: &mut M, pc: u64, insn: u32) -> Result<Commit, M::Error> {
+    let before = snapshot(model)?;
+    model.execute(insn)?;
+    let after = snapshot(model)?;
+
+    let mut effects = vec![];
+    for (i, (old, new)) in before.x.iter().zip(&after.x).enumerate() {
+        if old != new {
+            effects.push(Effect::Xreg {
+                reg: XReg::ALL[i],
+                value: *new,
+            });
+        }
+    }
+    let vlenb = model.vlen() as usize / 8;
+    for (i, (old, new)) in before
+        .v
+        .chunks(vlenb)
+        .zip(after.v.chunks(vlenb))
+        .enumerate()
+    {
+        if old != new {
+            effects.push(Effect::Vreg {
+                reg: VReg::ALL[i],
+                value: new.to_vec(),
+            });
+        }
+    }
+    for &(csr, old, new) in &[
+        (VL, before.vl, after.vl),
+        (VTYPE, before.vtype, after.vtype),
+    ] {
+        if old != new {
+            effects.push(Effect::Csr { csr, value: new });
+        }
+    }
+    Ok(Commit {
+        pc,
+        insn: insn as u64,
+        effects,
+    })
+}
+
+/// The registers [`execute_traced`] looks for changes in.
+struct Snapshot {
+    x: Vec<u64>,
+    v: Vec<u8>,
+    vl: u64,
+    vtype: u64,
+}
+
+fn snapshot<M: Model>(model: &mut M) -> Result<Snapshot, M::Error> {
+    let vlenb = model.vlen() as usize / 8;
+    let x = (0..32).map(|i| model.xreg(i)).collect::<Result<_, _>>()?;
+    let mut v = vec![0; 32 * vlenb];
+    for (i, reg) in v.chunks_mut(vlenb).enumerate() {
+        model.vreg(i, reg)?;
+    }
+    Ok(Snapshot {
+        x,
+        v,
+        vl: model.vl(),
+        vtype: model.vtype(),
+    })
+}
+
+/// A line of a commit log that couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a commit log written by [`Commit`]'s `Display`, `spike
+/// --log-commits` or another simulator in the same format.
+///
+/// Lines not starting with `core` are skipped, e.g. the program's output, as
+/// are the disassembly lines of `spike -l`, which have no privilege level.
+pub fn parse(log: &str) -> Result<Vec<Commit>, ParseError> {
+    let mut commits = vec![];
+    for (i, line) in log.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: i + 1,
+            message,
+        };
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("core") {
+            continue;
+        }
+        // The hart, e.g. `0:`.
+        tokens.next();
+        match tokens.next() {
+            Some(p) if p.len() == 1 && p.as_bytes()[0].is_ascii_digit() => {}
+            _ => continue,
+        }
+        let pc = tokens.next().unwrap_or("");
+        let pc = hex(pc).ok_or_else(|| error(format!("invalid pc `{}`", pc)))?;
+        let insn = tokens.next().unwrap_or("");
+        let insn = insn
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(hex)
+            .ok_or_else(|| error(format!("invalid instruction `{}`", insn)))?;
+
+        let mut effects = vec![];
+        while let Some(token) = tokens.next() {
+            let mut value = || {
+                let value = tokens.next().unwrap_or("");
+                hex_digits(value).ok_or_else(|| error(format!("invalid value `{}`", value)))
+            };
+            let effect = if token == "mem" {
+                let addr = u64::from_str_radix(value()?, 16)
+                    .map_err(|_| error("invalid address".to_string()))?;
+                // A store if a value follows.
+                match tokens.clone().next().and_then(hex_digits) {
+                    Some(digits) if digits.len() <= 16 => {
+                        tokens.next();
+                        Effect::Store {
+                            addr,
+                            value: u64::from_str_radix(digits, 16).unwrap(),
+                            size: digits.len() as u64 / 2,
+                        }
+                    }
+                    _ => Effect::Load { addr, size: 0 },
+                }
+            } else if let Some(reg) = register(token, 'x').and_then(XReg::new) {
+                let value = u64::from_str_radix(value()?, 16)
+                    .map_err(|_| error(format!("invalid value for `{}`", token)))?;
+                Effect::Xreg { reg, value }
+            } else if let Some(reg) = register(token, 'f').and_then(FReg::new) {
+                let value = u64::from_str_radix(value()?, 16)
+                    .map_err(|_| error(format!("invalid value for `{}`", token)))?;
+                Effect::Freg { reg, value }
+            } else if let Some(reg) = register(token, 'v').and_then(VReg::new) {
+                let digits = value()?;
+                let digits = if digits.len() % 2 == 1 {
+                    format!("0{}", digits)
+                } else {
+                    digits.to_string()
+                };
+                let value = (0..digits.len() / 2)
+                    .rev()
+                    .map(|i| u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).unwrap())
+                    .collect();
+                Effect::Vreg { reg, value }
+            } else if let Some(csr) = csr(token) {
+                let value = u64::from_str_radix(value()?, 16)
+                    .map_err(|_| error(format!("invalid value for `{}`", token)))?;
+                Effect::Csr { csr, value }
+            } else if vector_config(token) {
+                continue;
+            } else {
+                return Err(error(format!("unexpected `{}`", token)));
+            };
+            effects.push(effect);
+        }
+        effects.sort_by_key(order);
+        commits.push(Commit { pc, insn, effects });
+    }
+    Ok(commits)
+}
+
+/// The digits of a `0x` hex number.
+fn hex_digits(s: &str) -> Option<&str> {
+    s.strip_prefix("0x")
+        .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+fn hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+/// The number of a register token like `x10`.
+fn register(token: &str, prefix: char) -> Option<u32> {
+    let n = token.strip_prefix(prefix)?;
+    if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    n.parse().ok()
+}
+
+/// The number of a CSR token like `c768_mstatus`.
+fn csr(token: &str) -> Option<u32> {
+    let (n, _) = token.strip_prefix('c')?.split_once('_')?;
+    n.parse().ok()
+}
+
+/// Spike's vector configuration before vector register writes: `e64`, `m1`,
+/// `mf2` and `l4`.
+fn vector_config(token: &str) -> bool {
+    let n = match token.as_bytes().first() {
+        Some(b'e') | Some(b'l') => &token[1..],
+        Some(b'm') => token.strip_prefix("mf").unwrap_or(&token[1..]),
+        _ => return false,
+    };
+    !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x(n: u32, value: u64) -> Effect {
+        Effect::Xreg {
+            reg: XReg::new(n).unwrap(),
+            value,
+        }
+    }
+
+    #[test]
+    fn loads_and_stores() {
+        let log = "\
+core   0: 3 0x0000000000001000 (0x0005b503) x10 0x0000000000000005 mem 0x0000000000002000
+core   0: 3 0x0000000000001004 (0x00a5b023) mem 0x0000000000002000 0x0000000000000005
+core   0: 3 0x0000000000001008 (0x08b5252f) x10 0x0000000000000005 mem 0x0000000000002000 mem 0x0000000000002000 0x0000000b
+";
+        let commits = parse(log).unwrap();
+        assert_eq!(
+            commits[0].effects,
+            [
+                x(10, 5),
+                Effect::Load {
+                    addr: 0x2000,
+                    size: 0
+                }
+            ]
+        );
+        assert_eq!(
+            commits[1].effects,
+            [Effect::Store {
+                addr: 0x2000,
+                value: 5,
+                size: 8
+            }]
+        );
+        assert_eq!(
+            commits[2].effects,
+            [
+                x(10, 5),
+                Effect::Load {
+                    addr: 0x2000,
+                    size: 0
+                },
+                Effect::Store {
+                    addr: 0x2000,
+                    value: 0xb,
+                    size: 4
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn registers() {
+        let log = "core   0: 3 0x0000000000001000 (0x0202d057) c3104_vl 0x0000000000000002 \
+                   x10 0x0000000000000002 e64 mf2 l2 v1 0x123 f3 0x00000000ffffffff\n";
+        let commits = parse(log).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].pc, 0x1000);
+        assert_eq!(commits[0].insn, 0x0202d057);
+        // Sorted into x, f, v, CSR, and the vector configuration skipped.
+        assert_eq!(
+            commits[0].effects,
+            [
+                x(10, 2),
+                Effect::Freg {
+                    reg: FReg::new(3).unwrap(),
+                    value: 0xffff_ffff
+                },
+                Effect::Vreg {
+                    reg: VReg::new(1).unwrap(),
+                    value: vec![0x23, 0x01]
+                },
+                Effect::Csr { csr: VL, value: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_lines() {
+        let log = "\
+hello
+core   0: 0x0000000000001000 (0x00000513) li      a0, 0
+core   0: 3 0x0000000000001000 (0x00000513) x10 0x0000000000000000
+
+core   0: 0x0000000000001002 (0x0505) addi    a0, a0, 1
+core   0: 3 0x0000000000001002 (0x0505) x10 0x0000000000000001
+";
+        let commits = parse(log).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[1].pc, 0x1002);
+        assert_eq!(commits[1].insn, 0x0505);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |log| parse(log).unwrap_err();
+        assert_eq!(error("core   0: 3 1000 (0x00000513)").line, 1);
+        let e = error("\ncore   0: 3 0x1000 0x00000513");
+        assert_eq!(e.line, 2);
+        assert_eq!(e.message, "invalid instruction `0x00000513`");
+        let e = error("core   0: 3 0x1000 (0x00000513) x10");
+        assert_eq!(e.message, "invalid value ``");
+        let e = error("core   0: 3 0x1000 (0x00000513) y10 0x0");
+        assert_eq!(e.message, "unexpected `y10`");
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let commit = Commit {
+            pc: 0x1000,
+            insn: 0x0505,
+            effects: vec![
+                x(10, 1),
+                Effect::Vreg {
+                    reg: VReg::new(2).unwrap(),
+                    value: vec![1, 2, 3, 4],
+                },
+                Effect::Store {
+                    addr: 0x2000,
+                    value: 0xab,
+                    size: 1,
+                },
+            ],
+        };
+        assert_eq!(parse(&commit.to_string()).unwrap(), [commit]);
+    }
+}
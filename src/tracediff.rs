@@ -0,0 +1,320 @@
+//! Finding where two commit logs, e.g. one of Spike and one of ckb-vm,
+//! diverge; the library behind the `spike-sys-tracediff` binary.
+//!
+//! The logs are aligned at a common pc and then compared instruction by
+//! instruction: the pc, the encoding, and the register writes and memory
+//! accesses, in any order.
+use std::fmt::Write;
+
+use crate::disasm::Disassembler;
+use crate::trace::{Commit, Effect};
+
+/// How two commits at the same index differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Pc,
+    Insn,
+    /// The effects only one side has.
+    Effects {
+        left: Vec<Effect>,
+        right: Vec<Effect>,
+    },
+    /// One log ends here, the other goes on.
+    End,
+}
+
+/// The first instruction at which two logs differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the instruction in each log.
+    pub left: usize,
+    pub right: usize,
+    pub mismatch: Mismatch,
+}
+
+/// Where to start comparing two logs, whose first instructions may differ,
+/// e.g. if one of them includes Spike's boot ROM. That's the first commit of
+/// one log at the pc the other log starts at, or `None` if there's none.
+pub fn align(left: &[Commit], right: &[Commit]) -> Option<(usize, usize)> {
+    let (first_left, first_right) = match (left.first(), right.first()) {
+        (Some(l), Some(r)) => (l.pc, r.pc),
+        _ => return Some((0, 0)),
+    };
+    if let Some(i) = right.iter().position(|c| c.pc == first_left) {
+        return Some((0, i));
+    }
+    left.iter()
+        .position(|c| c.pc == first_right)
+        .map(|i| (i, 0))
+}
+
+/// Compare `left[start.0..]` with `right[start.1..]`, returning the first
+/// difference.
+pub fn diff(left: &[Commit], right: &[Commit], start: (usize, usize)) -> Option<Divergence> {
+    let (mut l, mut r) = start;
+    loop {
+        let mismatch = match (left.get(l), right.get(r)) {
+            (None, None) => return None,
+            (Some(_), None) | (None, Some(_)) => Mismatch::End,
+            (Some(a), Some(b)) if a.pc != b.pc => Mismatch::Pc,
+            (Some(a), Some(b)) if a.insn != b.insn => Mismatch::Insn,
+            (Some(a), Some(b)) => {
+                let (left, right) = unmatched(&a.effects, &b.effects);
+                if left.is_empty() && right.is_empty() {
+                    l += 1;
+                    r += 1;
+                    continue;
+                }
+                Mismatch::Effects { left, right }
+            }
+        };
+        return Some(Divergence {
+            left: l,
+            right: r,
+            mismatch,
+        });
+    }
+}
+
+/// The effects of `a` missing from `b` and those of `b` missing from `a`.
+fn unmatched(a: &[Effect], b: &[Effect]) -> (Vec<Effect>, Vec<Effect>) {
+    let mut only_b: Vec<Effect> = b.to_vec();
+    let mut only_a = vec![];
+    for effect in a {
+        match only_b.iter().position(|other| same(effect, other)) {
+            Some(i) => {
+                only_b.remove(i);
+            }
+            None => only_a.push(effect.clone()),
+        }
+    }
+    (only_a, only_b)
+}
+
+/// Equal, except that a load of unknown size matches loads of any size.
+fn same(a: &Effect, b: &Effect) -> bool {
+    match (a, b) {
+        (
+            Effect::Load { addr, size },
+            Effect::Load {
+                addr: other_addr,
+                size: other_size,
+            },
+        ) => addr == other_addr && (size == other_size || *size == 0 || *other_size == 0),
+        _ => a == b,
+    }
+}
+
+impl Divergence {
+    /// A summary of the difference followed by the `context` instructions
+    /// before it, which both logs agree on, and up to `context` instructions
+    /// from it on of each log, marked `<` and `>` as by diff(1).
+    /// Instructions are disassembled with `disasm`.
+    pub fn report(
+        &self,
+        left: &[Commit],
+        right: &[Commit],
+        context: usize,
+        disasm: &Disassembler,
+    ) -> String {
+        let mut out = String::new();
+        let index = if self.left == self.right {
+            format!("instruction {}", self.left)
+        } else {
+            format!("instruction {} (left) / {} (right)", self.left, self.right)
+        };
+        match &self.mismatch {
+            Mismatch::Pc => writeln!(
+                out,
+                "{}: pc 0x{:x} / 0x{:x}",
+                index, left[self.left].pc, right[self.right].pc
+            ),
+            Mismatch::Insn => writeln!(
+                out,
+                "{}: instruction 0x{:x} / 0x{:x}",
+                index, left[self.left].insn, right[self.right].insn
+            ),
+            Mismatch::Effects { left, right } => {
+                let show = |effects: &[Effect]| {
+                    if effects.is_empty() {
+                        return "nothing".to_string();
+                    }
+                    let effects: Vec<String> = effects.iter().map(|e| e.to_string()).collect();
+                    effects.join(", ")
+                };
+                writeln!(
+                    out,
+                    "{}: left has {}, right has {}",
+                    index,
+                    show(left),
+                    show(right)
+                )
+            }
+            Mismatch::End => {
+                let side = if self.left < left.len() {
+                    "right"
+                } else {
+                    "left"
+                };
+                writeln!(out, "{}: the {} log ends", index, side)
+            }
+        }
+        .unwrap();
+
+        let before = std::cmp::min(context, self.left.min(self.right));
+        for i in (1..=before).rev() {
+            line(&mut out, ' ', self.left - i, &left[self.left - i], disasm);
+        }
+        for (n, commit) in left.iter().enumerate().skip(self.left).take(context) {
+            line(&mut out, '<', n, commit, disasm);
+        }
+        for (n, commit) in right.iter().enumerate().skip(self.right).take(context) {
+            line(&mut out, '>', n, commit, disasm);
+        }
+        out.trim_end().to_string()
+    }
+}
+
+fn line(out: &mut String, marker: char, index: usize, commit: &Commit, disasm: &Disassembler) {
+    let text = disasm
+        .disassemble_at(commit.insn, commit.pc)
+        .unwrap_or_else(|_| "(bad)".to_string());
+    let mut line = format!(
+        "{} {:>6} 0x{:016x} (0x{:08x}) {:<32}",
+        marker, index, commit.pc, commit.insn, text
+    );
+    for effect in &commit.effects {
+        write!(line, " {}", effect).unwrap();
+    }
+    writeln!(out, "{}", line.trim_end()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::insn::XReg;
+
+    fn commit(pc: u64, effects: Vec<Effect>) -> Commit {
+        Commit {
+            pc,
+            insn: 0x00000013,
+            effects,
+        }
+    }
+
+    fn x(n: u32, value: u64) -> Effect {
+        Effect::Xreg {
+            reg: XReg::new(n).unwrap(),
+            value,
+        }
+    }
+
+    fn load(addr: u64, size: u64) -> Effect {
+        Effect::Load { addr, size }
+    }
+
+    /// A log running from `start`, writing each instruction's index to x10.
+    fn log(start: u64, len: u64) -> Vec<Commit> {
+        (0..len)
+            .map(|i| commit(start + 4 * i, vec![x(10, i)]))
+            .collect()
+    }
+
+    #[test]
+    fn alignment() {
+        let program = log(0x1000, 4);
+        let mut with_boot_rom = log(0x100, 3);
+        with_boot_rom.extend(program.iter().cloned());
+        assert_eq!(align(&program, &program), Some((0, 0)));
+        assert_eq!(align(&with_boot_rom, &program), Some((3, 0)));
+        assert_eq!(align(&program, &with_boot_rom), Some((0, 3)));
+        assert_eq!(align(&program, &program[2..]), Some((2, 0)));
+        assert_eq!(align(&program, &log(0x2000, 4)), None);
+        assert_eq!(align(&[], &program), Some((0, 0)));
+    }
+
+    #[test]
+    fn divergence() {
+        let left = log(0x1000, 4);
+        assert_eq!(diff(&left, &left, (0, 0)), None);
+        assert_eq!(diff(&left, &left[1..], (1, 0)), None);
+
+        let mut right = left.clone();
+        right[2].effects = vec![x(10, 7)];
+        assert_eq!(
+            diff(&left, &right, (0, 0)),
+            Some(Divergence {
+                left: 2,
+                right: 2,
+                mismatch: Mismatch::Effects {
+                    left: vec![x(10, 2)],
+                    right: vec![x(10, 7)],
+                },
+            })
+        );
+
+        right[2].insn = 0x00100513;
+        assert_eq!(
+            diff(&left, &right, (0, 0)).unwrap().mismatch,
+            Mismatch::Insn
+        );
+        right[2].pc = 0x2000;
+        assert_eq!(diff(&left, &right, (0, 0)).unwrap().mismatch, Mismatch::Pc);
+    }
+
+    #[test]
+    fn prefix() {
+        let left = log(0x1000, 4);
+        let expected = Some(Divergence {
+            left: 3,
+            right: 3,
+            mismatch: Mismatch::End,
+        });
+        assert_eq!(diff(&left, &left[..3], (0, 0)), expected);
+        assert_eq!(diff(&left[..3], &left, (0, 0)), expected);
+
+        let mut with_boot_rom = log(0x100, 2);
+        with_boot_rom.extend(left[..3].iter().cloned());
+        let start = align(&with_boot_rom, &left).unwrap();
+        assert_eq!(
+            diff(&with_boot_rom, &left, start),
+            Some(Divergence {
+                left: 5,
+                right: 3,
+                mismatch: Mismatch::End,
+            })
+        );
+    }
+
+    #[test]
+    fn effects_in_any_order() {
+        let a = [x(10, 1), x(11, 2), load(0x2000, 4)];
+        let b = [load(0x2000, 4), x(11, 2), x(10, 1)];
+        assert_eq!(unmatched(&a, &b), (vec![], vec![]));
+        let b = [x(11, 2), x(12, 1)];
+        assert_eq!(
+            unmatched(&a, &b),
+            (vec![x(10, 1), load(0x2000, 4)], vec![x(12, 1)])
+        );
+    }
+
+    #[test]
+    fn loads_of_unknown_size() {
+        assert_eq!(
+            unmatched(&[load(0x2000, 0)], &[load(0x2000, 8)]),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            unmatched(&[load(0x2000, 4)], &[load(0x2000, 0)]),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            unmatched(&[load(0x2000, 4)], &[load(0x2000, 8)]),
+            (vec![load(0x2000, 4)], vec![load(0x2000, 8)])
+        );
+        assert_eq!(
+            unmatched(&[load(0x2000, 0)], &[load(0x2008, 0)]),
+            (vec![load(0x2000, 0)], vec![load(0x2008, 0)])
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! The `vtype` CSR and generation of `vsetvli`/`vsetivli`/`vsetvl` instructions.
+use rand_core::RngCore;
+
+use crate::Model;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lmul {
+    Mf8,
+    Mf4,
+    Mf2,
+    M1,
+    M2,
+    M4,
+    M8,
+}
+
+impl Lmul {
+    pub const ALL: [Lmul; 7] = [
+        Lmul::M1,
+        Lmul::M2,
+        Lmul::M4,
+        Lmul::M8,
+        Lmul::Mf2,
+        Lmul::Mf4,
+        Lmul::Mf8,
+    ];
+
+    /// Decode the 3-bit `vlmul` field. `0b100` is reserved.
+    pub fn from_bits(bits: u64) -> Option<Lmul> {
+        match bits & 0b111 {
+            0b000 => Some(Lmul::M1),
+            0b001 => Some(Lmul::M2),
+            0b010 => Some(Lmul::M4),
+            0b011 => Some(Lmul::M8),
+            0b101 => Some(Lmul::Mf8),
+            0b110 => Some(Lmul::Mf4),
+            0b111 => Some(Lmul::Mf2),
+            _ => None,
+        }
+    }
+
+    pub fn bits(self) -> u64 {
+        match self {
+            Lmul::M1 => 0b000,
+            Lmul::M2 => 0b001,
+            Lmul::M4 => 0b010,
+            Lmul::M8 => 0b011,
+            Lmul::Mf8 => 0b101,
+            Lmul::Mf4 => 0b110,
+            Lmul::Mf2 => 0b111,
+        }
+    }
+
+    /// LMUL in units of 1/8, so that fractional values stay integers.
+    pub fn eighths(self) -> u32 {
+        match self {
+            Lmul::Mf8 => 1,
+            Lmul::Mf4 => 2,
+            Lmul::Mf2 => 4,
+            Lmul::M1 => 8,
+            Lmul::M2 => 16,
+            Lmul::M4 => 32,
+            Lmul::M8 => 64,
+        }
+    }
+
+    pub fn as_f32(self) -> f32 {
+        self.eighths() as f32 / 8.0
+    }
+
+    /// The number of registers in a register group, 1 for fractional LMUL.
+    pub fn regs(self) -> u32 {
+        std::cmp::max(1, self.eighths() / 8)
+    }
+}
+
+impl std::fmt::Display for Lmul {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Lmul::Mf8 => "mf8",
+            Lmul::Mf4 => "mf4",
+            Lmul::Mf2 => "mf2",
+            Lmul::M1 => "m1",
+            Lmul::M2 => "m2",
+            Lmul::M4 => "m4",
+            Lmul::M8 => "m8",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VType {
+    /// SEW in bits, 8 to 1024.
+    pub sew: u32,
+    pub lmul: Lmul,
+    pub vta: bool,
+    pub vma: bool,
+}
+
+impl VType {
+    pub fn new(sew: u32, lmul: Lmul) -> Self {
+        Self {
+            sew,
+            lmul,
+            vta: false,
+            vma: false,
+        }
+    }
+
+    /// Decode a `vtype` value as read back from the CSR. Returns `None` if
+    /// `vill` is set or a field is reserved.
+    pub fn from_bits(bits: u64) -> Option<VType> {
+        if bits >> 8 != 0 {
+            return None;
+        }
+        Some(Self {
+            sew: 8 << ((bits >> 3) & 0b111),
+            lmul: Lmul::from_bits(bits)?,
+            vta: bits & (1 << 6) != 0,
+            vma: bits & (1 << 7) != 0,
+        })
+    }
+
+    /// Parse the assembler syntax printed by `Display`, e.g. `e64, m1, ta, mu`.
+    /// LMUL defaults to m1 and the policies to undisturbed.
+    pub fn parse(s: &str) -> Option<VType> {
+        let mut vtype = VType::new(0, Lmul::M1);
+        for token in s.split(',').map(str::trim) {
+            match token {
+                "ta" => vtype.vta = true,
+                "tu" => vtype.vta = false,
+                "ma" => vtype.vma = true,
+                "mu" => vtype.vma = false,
+                _ => {
+                    if let Some(lmul) = Lmul::ALL.iter().find(|l| l.to_string() == token) {
+                        vtype.lmul = *lmul;
+                    } else {
+                        let sew: u32 = token.strip_prefix('e')?.parse().ok()?;
+                        if !sew.is_power_of_two() || !(8..=1024).contains(&sew) {
+                            return None;
+                        }
+                        vtype.sew = sew;
+                    }
+                }
+            }
+        }
+        if vtype.sew == 0 {
+            return None;
+        }
+        Some(vtype)
+    }
+
+    pub fn bits(&self) -> u64 {
+        let vsew = self.sew.trailing_zeros() as u64 - 3;
+        (self.vma as u64) << 7 | (self.vta as u64) << 6 | vsew << 3 | self.lmul.bits()
+    }
+
+    /// Whether an implementation with this ELEN supports the vtype, i.e.
+    /// SEW <= ELEN and SEW <= LMUL * ELEN.
+    pub fn is_legal(&self, elen: u32) -> bool {
+        self.sew <= elen && self.sew * 8 <= elen * self.lmul.eighths()
+    }
+
+    /// VLMAX = LMUL * VLEN / SEW.
+    pub fn vlmax(&self, vlen: u32) -> u32 {
+        (vlen as u64 * self.lmul.eighths() as u64 / 8 / self.sew as u64) as u32
+    }
+
+    /// The number of registers occupied by an operand with element width `eew`,
+    /// or `None` if its EMUL falls outside [1/8, 8].
+    pub fn emul_regs(&self, eew: u32) -> Option<u32> {
+        let eighths = self.lmul.eighths() as u64 * eew as u64;
+        if eighths % self.sew as u64 != 0 {
+            return None;
+        }
+        let eighths = eighths / self.sew as u64;
+        if !(1..=64).contains(&eighths) {
+            return None;
+        }
+        Some(std::cmp::max(1, eighths as u32 / 8))
+    }
+}
+
+impl std::fmt::Display for VType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "e{}, {}, {}, {}",
+            self.sew,
+            self.lmul,
+            if self.vta { "ta" } else { "tu" },
+            if self.vma { "ma" } else { "mu" }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VSetForm {
+    /// `vsetvli rd, rs1, vtypei`
+    Vsetvli,
+    /// `vsetivli rd, uimm, vtypei`
+    Vsetivli,
+    /// `vsetvl rd, rs1, rs2`
+    Vsetvl,
+}
+
+/// A configuration-setting instruction together with the register values it reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VSet {
+    pub form: VSetForm,
+    /// The raw vtype value, which may be reserved.
+    pub vtype: u64,
+    pub avl: u64,
+    pub rd: u32,
+    /// Holds AVL for `vsetvli` and `vsetvl`.
+    pub rs1: u32,
+    /// Holds vtype for `vsetvl`.
+    pub rs2: u32,
+}
+
+impl VSet {
+    pub fn encode(&self) -> u32 {
+        let rd = self.rd << 7;
+        match self.form {
+            VSetForm::Vsetvli => {
+                (self.vtype as u32 & 0x7ff) << 20 | self.rs1 << 15 | 0b111 << 12 | rd | 0b1010111
+            }
+            VSetForm::Vsetivli => {
+                0b11 << 30
+                    | (self.vtype as u32 & 0x3ff) << 20
+                    | (self.avl as u32 & 0b11111) << 15
+                    | 0b111 << 12
+                    | rd
+                    | 0b1010111
+            }
+            VSetForm::Vsetvl => {
+                0b1000000 << 25 | self.rs2 << 20 | self.rs1 << 15 | 0b111 << 12 | rd | 0b1010111
+            }
+        }
+    }
+
+    /// The x registers to set before executing the instruction.
+    pub fn inputs(&self) -> Vec<(u32, u64)> {
+        match self.form {
+            VSetForm::Vsetvli => vec![(self.rs1, self.avl)],
+            VSetForm::Vsetivli => vec![],
+            VSetForm::Vsetvl => vec![(self.rs1, self.avl), (self.rs2, self.vtype)],
+        }
+    }
+
+    /// The vtype seen by the hart, `None` if vill will be set.
+    pub fn decoded(&self, elen: u32) -> Option<VType> {
+        let bits = match self.form {
+            VSetForm::Vsetvli => self.vtype & 0x7ff,
+            VSetForm::Vsetivli => self.vtype & 0x3ff,
+            VSetForm::Vsetvl => self.vtype,
+        };
+        VType::from_bits(bits).filter(|vtype| vtype.is_legal(elen))
+    }
+
+    /// The vl Spike sets: 0 if vill, otherwise min(AVL, VLMAX).
+    pub fn expected_vl(&self, vlen: u32, elen: u32) -> u64 {
+        match self.decoded(elen) {
+            Some(vtype) => std::cmp::min(self.avl, vtype.vlmax(vlen) as u64),
+            None => 0,
+        }
+    }
+
+    /// Write the input registers and execute the instruction on `model`.
+    pub fn apply<M: Model>(&self, model: &mut M) -> Result<(), M::Error> {
+        for (reg, value) in self.inputs() {
+            model.set_xreg(reg as usize, value)?;
+        }
+        model.execute(self.encode())
+    }
+}
+
+/// Generates vtype/AVL combinations for a given VLEN and ELEN.
+pub struct VTypeGenerator {
+    pub vlen: u32,
+    pub elen: u32,
+    /// Also produce vtypes that set vill: reserved LMUL, SEW > ELEN or SEW > LMUL * ELEN.
+    pub allow_illegal: bool,
+    /// Randomise vta/vma. Models may legitimately disagree on the values of
+    /// agnostic elements, so this is off by default.
+    pub agnostic: bool,
+    pub rd: u32,
+    pub avl_reg: u32,
+    pub vtype_reg: u32,
+}
+
+impl VTypeGenerator {
+    /// Registers default to the ones the fuzz harnesses use: rd = t0, AVL in x4, vtype in x3.
+    pub fn new(vlen: u32, elen: u32) -> Self {
+        Self {
+            vlen,
+            elen,
+            allow_illegal: false,
+            agnostic: false,
+            rd: 5,
+            avl_reg: 4,
+            vtype_reg: 3,
+        }
+    }
+
+    /// Every legal SEW/LMUL combination, with tail and mask undisturbed.
+    pub fn legal_vtypes(&self) -> Vec<VType> {
+        let mut r = vec![];
+        let mut sew = 8;
+        while sew <= std::cmp::min(self.elen, 1024) {
+            for lmul in Lmul::ALL.iter() {
+                let vtype = VType::new(sew, *lmul);
+                if vtype.is_legal(self.elen) {
+                    r.push(vtype);
+                }
+            }
+            sew *= 2;
+        }
+        r
+    }
+
+    /// Generate with a random form.
+    pub fn generate<R: RngCore>(&self, rng: &mut R) -> VSet {
+        let form =
+            [VSetForm::Vsetvli, VSetForm::Vsetivli, VSetForm::Vsetvl][rng.next_u32() as usize % 3];
+        self.generate_form(rng, form)
+    }
+
+    /// Generate a `form` instruction. AVL is in [0, VLMAX], and also limited
+    /// to 31 for `vsetivli`.
+    pub fn generate_form<R: RngCore>(&self, rng: &mut R, form: VSetForm) -> VSet {
+        let vtype = if self.allow_illegal {
+            let bits = rng.next_u32() as u64;
+            let vtype = bits & 0xff;
+            // Occasionally set a reserved high bit too.
+            if bits & 0x0f00 == 0 {
+                vtype | 1 << (8 + (bits >> 12) % 2)
+            } else {
+                vtype
+            }
+        } else {
+            let vtypes = self.legal_vtypes();
+            let mut vtype = vtypes[rng.next_u32() as usize % vtypes.len()];
+            if self.agnostic {
+                let bits = rng.next_u32();
+                vtype.vta = bits & 1 != 0;
+                vtype.vma = bits & 2 != 0;
+            }
+            vtype.bits()
+        };
+        let vlmax = match VType::from_bits(vtype) {
+            Some(v) if v.is_legal(self.elen) => v.vlmax(self.vlen) as u64,
+            _ => (self.vlen / 8) as u64,
+        };
+        let mut avl = rng.next_u64() % (vlmax + 1);
+        if form == VSetForm::Vsetivli {
+            avl %= 32;
+        }
+        VSet {
+            form,
+            vtype,
+            avl,
+            rd: self.rd,
+            rs1: self.avl_reg,
+            rs2: self.vtype_reg,
+        }
+    }
+}
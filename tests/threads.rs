@@ -0,0 +1,67 @@
+use std::thread;
+
+use spike_sys::insn::{VInsn, VReg};
+use spike_sys::Spike;
+
+const PROCESSORS: usize = 256;
+const ROUNDS: usize = 100;
+
+/// 1/3 in every lane of v2 under rounding mode `frm`, with fflags in x6.
+fn run(spike: &Spike, frm: u32) -> (Vec<u8>, u64) {
+    spike
+        .execute_asm(&format!(
+            "
+            vsetivli t0, 2, e64, m1
+            li t0, 0x6000
+            csrrs zero, 0x100, t0 # sstatus.FS
+            csrrwi zero, frm, {}
+            csrrwi zero, fflags, 0
+            ",
+            frm
+        ))
+        .unwrap();
+    let one = [1.0f64.to_le_bytes(), 1.0f64.to_le_bytes()].concat();
+    let three = [3.0f64.to_le_bytes(), 3.0f64.to_le_bytes()].concat();
+    spike.set_vreg(16 * 10, &one).unwrap();
+    spike.set_vreg(16 * 20, &three).unwrap();
+    spike
+        .execute(VInsn::vfdiv_vv(VReg::V2, VReg::V10, VReg::V20).encode() as u64)
+        .unwrap();
+    spike.execute_asm("csrrs t1, fflags, zero").unwrap();
+
+    let mut result = vec![0u8; 16];
+    spike.get_vreg(16 * 2, &mut result).unwrap();
+    (result, spike.get_xreg(6).unwrap())
+}
+
+#[test]
+fn concurrent_processors() {
+    // Round to nearest and round up give different results for 1/3, so a
+    // rounding mode leaking between threads through softfloat's globals
+    // shows up as a mismatch.
+    let expected: Vec<_> = [0, 3]
+        .iter()
+        .map(|frm| run(&Spike::new(128, 64, 0).unwrap(), *frm))
+        .collect();
+    assert_ne!(expected[0].0, expected[1].0);
+    assert_eq!(expected[0].1, 1); // NX
+
+    let processors: Vec<Spike> = (0..PROCESSORS)
+        .map(|_| Spike::new(128, 64, 0).unwrap())
+        .collect();
+    let handles: Vec<_> = processors
+        .into_iter()
+        .enumerate()
+        .map(|(i, spike)| {
+            thread::spawn(move || {
+                let frm = [0, 3][i % 2];
+                (0..ROUNDS).map(|_| run(&spike, frm)).collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        for result in handle.join().unwrap() {
+            assert_eq!(result, expected[i % 2]);
+        }
+    }
+}